@@ -0,0 +1,80 @@
+//! Making sure the TLS connections to APNs speak HTTP/2
+
+use hyper::Uri;
+use hyper_rustls::MaybeHttpsStream;
+use std::error::Error as StdError;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use thiserror::Error;
+use tower_service::Service;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// The TLS handshake finished without the server agreeing to HTTP/2.
+#[derive(Debug, Error)]
+#[error("the server did not negotiate HTTP/2")]
+pub(crate) struct Http2NotNegotiated;
+
+/// Fails the TLS connections that did not negotiate HTTP/2 with ALPN, which
+/// hyper would otherwise try to speak HTTP/2 over and fail with a protocol
+/// error that doesn't tell what went wrong.
+#[derive(Debug, Clone)]
+pub(crate) struct Http2Connector<C> {
+    inner: C,
+}
+
+impl<C> Http2Connector<C> {
+    pub(crate) fn new(inner: C) -> Self {
+        Self { inner }
+    }
+}
+
+impl<C, T> Service<Uri> for Http2Connector<C>
+where
+    C: Service<Uri, Response = MaybeHttpsStream<T>, Error = BoxError>,
+    C::Future: Send + 'static,
+    T: Send + 'static,
+{
+    type Response = MaybeHttpsStream<T>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = self.inner.call(uri);
+
+        Box::pin(async move {
+            let stream = connecting.await?;
+
+            if let MaybeHttpsStream::Https(ref tls) = stream {
+                let (_, session) = tls.inner().get_ref();
+
+                if session.alpn_protocol() != Some(b"h2") {
+                    return Err(Http2NotNegotiated.into());
+                }
+            }
+
+            Ok(stream)
+        })
+    }
+}
+
+/// True if the error, or one of its sources, is a connection that did not
+/// negotiate HTTP/2.
+pub(crate) fn is_http2_not_negotiated(error: &(dyn StdError + 'static)) -> bool {
+    let mut source = Some(error);
+
+    while let Some(error) = source {
+        if error.is::<Http2NotNegotiated>() {
+            return true;
+        }
+
+        source = error.source();
+    }
+
+    false
+}
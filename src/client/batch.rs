@@ -0,0 +1,187 @@
+//! Splitting large sends into batches and summing up their results
+
+use crate::error::Error;
+use crate::response::{ErrorReason, Response};
+use std::collections::HashMap;
+
+/// An iterator over batches of device tokens, created by [`chunk_tokens`].
+#[derive(Debug, Clone)]
+pub struct TokenChunks<I> {
+    tokens: I,
+    size: usize,
+}
+
+/// Splits the tokens into batches of `concurrency` tokens, the amount
+/// [`Client::send_stream`](super::Client::send_stream) keeps in flight with
+/// the same `concurrency`. Every batch then fills the connection exactly
+/// once, and a batch can be sent, checked and retried as a unit while the
+/// tokens are read lazily. A concurrency of zero is treated as one, like
+/// `send_stream` does.
+///
+/// ```rust
+/// # use a2::client::chunk_tokens;
+/// # fn main() {
+/// let tokens = (0..2500).map(|i| format!("token_{}", i));
+/// let sizes: Vec<usize> = chunk_tokens(tokens, 1000).map(|chunk| chunk.len()).collect();
+///
+/// assert_eq!(vec![1000, 1000, 500], sizes);
+/// # }
+/// ```
+pub fn chunk_tokens<I: IntoIterator>(tokens: I, concurrency: usize) -> TokenChunks<I::IntoIter> {
+    TokenChunks {
+        tokens: tokens.into_iter(),
+        size: concurrency.max(1),
+    }
+}
+
+impl<I: Iterator> Iterator for TokenChunks<I> {
+    type Item = Vec<I::Item>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let chunk: Vec<I::Item> = self.tokens.by_ref().take(self.size).collect();
+
+        if chunk.is_empty() {
+            None
+        } else {
+            Some(chunk)
+        }
+    }
+}
+
+/// The outcome of a batch of sends, e.g. the results of
+/// [`Client::send_stream`](super::Client::send_stream), counted up.
+///
+/// ```rust
+/// # use a2::client::BatchSummary;
+/// # use a2::Error;
+/// # fn main() {
+/// let results = vec![(String::from("token"), Err(Error::InvalidPayload(String::from("conflict"))))];
+/// let summary = BatchSummary::summarize(&results);
+///
+/// assert_eq!(1, summary.total);
+/// assert_eq!(1, summary.other_failures);
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    /// The number of sends
+    pub total: usize,
+    /// The sends accepted by APNs
+    pub successes: usize,
+    /// The sends rejected by APNs, by the reason of the rejection
+    pub failures_by_reason: HashMap<ErrorReason, usize>,
+    /// The failed sends without a reason from APNs, e.g. connection errors
+    /// or error responses from a proxy
+    pub other_failures: usize,
+    /// The device tokens APNs reported as inactive, to be removed from the
+    /// stored tokens
+    pub tokens_to_prune: Vec<String>,
+}
+
+impl BatchSummary {
+    /// Counts up the device tokens and results of a batch.
+    pub fn summarize<'r, I>(results: I) -> Self
+    where
+        I: IntoIterator<Item = &'r (String, Result<Response, Error>)>,
+    {
+        let mut summary = Self::default();
+
+        for (device_token, result) in results {
+            summary.record(device_token, result);
+        }
+
+        summary
+    }
+
+    /// Adds the result of a single send, for summing up results as they
+    /// arrive.
+    pub fn record(&mut self, device_token: &str, result: &Result<Response, Error>) {
+        self.total += 1;
+
+        match result {
+            Ok(_) => self.successes += 1,
+            Err(Error::ResponseError(response)) => {
+                match response.error {
+                    Some(ref error) => *self.failures_by_reason.entry(error.reason).or_default() += 1,
+                    None => self.other_failures += 1,
+                }
+
+                if response.should_remove_token() {
+                    self.tokens_to_prune.push(device_token.to_string());
+                }
+            }
+            Err(_) => self.other_failures += 1,
+        }
+    }
+
+    /// The number of failed sends.
+    pub fn failures(&self) -> usize {
+        self.total - self.successes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_of_a_mixed_batch() {
+        let results = vec![
+            (String::from("a"), Ok(Response::new(200, None))),
+            (String::from("b"), Ok(Response::new(200, None))),
+            (
+                String::from("c"),
+                Err(Error::ResponseError(Box::new(Response::new(
+                    410,
+                    Some(ErrorReason::Unregistered),
+                )))),
+            ),
+            (
+                String::from("d"),
+                Err(Error::ResponseError(Box::new(Response::new(
+                    400,
+                    Some(ErrorReason::BadDeviceToken),
+                )))),
+            ),
+            (
+                String::from("e"),
+                Err(Error::ResponseError(Box::new(Response::new(
+                    400,
+                    Some(ErrorReason::BadDeviceToken),
+                )))),
+            ),
+            (
+                String::from("f"),
+                Err(Error::ResponseError(Box::new(Response::new(502, None)))),
+            ),
+            (String::from("g"), Err(Error::RequestTimeout(20))),
+        ];
+
+        let summary = BatchSummary::summarize(&results);
+
+        assert_eq!(7, summary.total);
+        assert_eq!(2, summary.successes);
+        assert_eq!(5, summary.failures());
+        assert_eq!(
+            HashMap::from([(ErrorReason::Unregistered, 1), (ErrorReason::BadDeviceToken, 2)]),
+            summary.failures_by_reason
+        );
+        assert_eq!(2, summary.other_failures);
+        assert_eq!(vec![String::from("c")], summary.tokens_to_prune);
+    }
+
+    #[test]
+    fn test_chunks_respect_the_concurrency() {
+        let chunks: Vec<Vec<u32>> = chunk_tokens(0..10, 4).collect();
+
+        assert_eq!(vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7], vec![8, 9]], chunks);
+    }
+
+    #[test]
+    fn test_chunks_with_zero_concurrency() {
+        let chunks: Vec<Vec<&str>> = chunk_tokens(["a", "b"], 0).collect();
+
+        assert_eq!(vec![vec!["a"], vec!["b"]], chunks);
+        assert_eq!(0, chunk_tokens(Vec::<String>::new(), 10).count());
+    }
+}
@@ -0,0 +1,156 @@
+//! Bounding the connections of several clients together
+
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper::Uri;
+use hyper_util::client::legacy::connect::{Connected, Connection};
+use std::error::Error as StdError;
+use std::future::Future;
+use std::io::{self, IoSlice};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tower_service::Service;
+
+type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// The maximum number of connections open at the same time, shared by the
+/// clients given clones of it with
+/// [`ClientConfig::with_connection_limit`](super::ClientConfig::with_connection_limit),
+/// e.g. the clients of several teams or of the production and sandbox
+/// environments.
+///
+/// A connection takes up its slot until it is closed, including the time
+/// it is kept idle in the pool of its client. A client that needs a new
+/// connection while all slots are taken waits for one to free up, up to
+/// the timeout of the request.
+///
+/// ```rust
+/// # use a2::client::{ClientConfig, ConnectionLimit};
+/// # use a2::Endpoint;
+/// # fn main() {
+/// let limit = ConnectionLimit::new(4);
+///
+/// let production = ClientConfig::new(Endpoint::Production).with_connection_limit(limit.clone());
+/// let sandbox = ClientConfig::new(Endpoint::Sandbox).with_connection_limit(limit.clone());
+///
+/// assert_eq!(4, limit.available());
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectionLimit(Arc<Semaphore>);
+
+impl ConnectionLimit {
+    /// Allows `max_connections` connections at a time.
+    pub fn new(max_connections: usize) -> Self {
+        Self(Arc::new(Semaphore::new(max_connections)))
+    }
+
+    /// The number of connections that can still be opened.
+    pub fn available(&self) -> usize {
+        self.0.available_permits()
+    }
+}
+
+/// Waits for a free slot of the connection limit before connecting, holding
+/// the slot for the lifetime of the connection.
+#[derive(Debug, Clone)]
+pub(crate) struct LimitedConnector<C> {
+    inner: C,
+    limit: Option<ConnectionLimit>,
+}
+
+impl<C> LimitedConnector<C> {
+    pub(crate) fn new(inner: C, limit: Option<ConnectionLimit>) -> Self {
+        Self { inner, limit }
+    }
+}
+
+impl<C> Service<Uri> for LimitedConnector<C>
+where
+    C: Service<Uri> + Clone + Send + 'static,
+    C::Error: Into<BoxError>,
+    C::Future: Send + 'static,
+{
+    type Response = LimitedStream<C::Response>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, BoxError>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BoxError>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let Some(limit) = self.limit.clone() else {
+            let connecting = self.inner.call(uri);
+
+            return Box::pin(async move {
+                let stream = connecting.await.map_err(Into::into)?;
+                Ok(LimitedStream {
+                    inner: stream,
+                    _permit: None,
+                })
+            });
+        };
+
+        // The inner connector was made ready for this call, the clone taking
+        // its place is made ready on the next call.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let permit = limit.0.acquire_owned().await?;
+            let stream = inner.call(uri).await.map_err(Into::into)?;
+
+            Ok(LimitedStream {
+                inner: stream,
+                _permit: Some(permit),
+            })
+        })
+    }
+}
+
+/// A connection holding its slot of the connection limit.
+#[derive(Debug)]
+pub(crate) struct LimitedStream<T> {
+    inner: T,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl<T: Read + Unpin> Read for LimitedStream<T> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: ReadBufCursor<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T: Write + Unpin> Write for LimitedStream<T> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+}
+
+impl<T: Connection> Connection for LimitedStream<T> {
+    fn connected(&self) -> Connected {
+        self.inner.connected()
+    }
+}
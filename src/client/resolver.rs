@@ -0,0 +1,413 @@
+//! Name resolution for the connections to APNs
+
+use super::ClientConfig;
+use hyper::Uri;
+use hyper_util::client::legacy::connect::dns::{GaiResolver, Name};
+use parking_lot::Mutex;
+use std::future::{self, Future};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use std::{io, vec};
+use tower_service::Service;
+
+/// Which IP versions to use when connecting to APNs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IpPreference {
+    /// Connect using the addresses in the order the system resolver returns
+    /// them. If the first address family does not connect within 300
+    /// milliseconds, the other family is tried concurrently and the first
+    /// connection to succeed is used (happy eyeballs).
+    #[default]
+    DualStack,
+    /// Same as `DualStack`, but try the IPv4 addresses first.
+    PreferIpv4,
+    /// Never connect over IPv6.
+    Ipv4Only,
+}
+
+impl IpPreference {
+    fn apply(self, addrs: impl Iterator<Item = SocketAddr>) -> io::Result<Vec<SocketAddr>> {
+        let mut addrs: Vec<SocketAddr> = addrs.collect();
+
+        match self {
+            IpPreference::DualStack => (),
+            IpPreference::PreferIpv4 => addrs.sort_by_key(|addr| addr.is_ipv6()),
+            IpPreference::Ipv4Only => {
+                addrs.retain(|addr| addr.is_ipv4());
+
+                if addrs.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::NotFound, "no IPv4 address found"));
+                }
+            }
+        }
+
+        Ok(addrs)
+    }
+}
+
+/// Resolved addresses and when they were resolved.
+type Resolved = (Vec<SocketAddr>, Instant);
+
+/// The addresses of the endpoint, used for the connections instead of
+/// resolving the host name again until they are older than the TTL or a
+/// connection to them fails.
+#[derive(Debug, Clone)]
+pub(crate) struct AddrCache {
+    addrs: Arc<Mutex<Option<Resolved>>>,
+    ttl: Duration,
+}
+
+impl AddrCache {
+    fn new(addr: SocketAddr, ttl: Duration) -> Self {
+        Self {
+            addrs: Arc::new(Mutex::new(Some((vec![addr], Instant::now())))),
+            ttl,
+        }
+    }
+
+    /// The cached addresses, if they are still fresh.
+    fn get(&self) -> Option<Vec<SocketAddr>> {
+        match *self.addrs.lock() {
+            Some((ref addrs, resolved_at)) if resolved_at.elapsed() < self.ttl => Some(addrs.clone()),
+            _ => None,
+        }
+    }
+
+    fn set(&self, addrs: Vec<SocketAddr>) {
+        *self.addrs.lock() = Some((addrs, Instant::now()));
+    }
+
+    /// Makes the next connection resolve the host name again.
+    fn invalidate(&self) {
+        *self.addrs.lock() = None;
+    }
+}
+
+/// Resolves the host names with the given resolver, ordering the addresses
+/// by the configured [`IpPreference`]. With a host override the resolver is
+/// skipped and every name resolves to the override address. With an address
+/// cache the resolver is only asked once the cached addresses are stale.
+#[derive(Debug, Clone)]
+pub(crate) struct Resolver<R = GaiResolver> {
+    inner: R,
+    ip_preference: IpPreference,
+    host_override: Option<SocketAddr>,
+    cache: Option<AddrCache>,
+}
+
+impl Resolver {
+    pub(crate) fn new(config: &ClientConfig) -> Self {
+        Self {
+            host_override: config.host_override,
+            cache: config
+                .resolved_addr
+                .map(|addr| AddrCache::new(addr, Duration::from_secs(config.resolved_addr_ttl_secs))),
+            ..Self::with_inner(GaiResolver::new(), config.ip_preference)
+        }
+    }
+}
+
+impl<R> Resolver<R> {
+    fn with_inner(inner: R, ip_preference: IpPreference) -> Self {
+        Self {
+            inner,
+            ip_preference,
+            host_override: None,
+            cache: None,
+        }
+    }
+
+    /// The address cache shared with the clones of the resolver.
+    pub(crate) fn cache(&self) -> Option<AddrCache> {
+        self.cache.clone()
+    }
+}
+
+impl<R> Service<Name> for Resolver<R>
+where
+    R: Service<Name, Error = io::Error>,
+    R::Response: Iterator<Item = SocketAddr>,
+    R::Future: Send + 'static,
+{
+    type Response = vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = io::Result<Self::Response>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        if let Some(addr) = self.host_override {
+            return Box::pin(future::ready(Ok(vec![addr].into_iter())));
+        }
+
+        if let Some(addrs) = self.cache.as_ref().and_then(AddrCache::get) {
+            return Box::pin(future::ready(Ok(addrs.into_iter())));
+        }
+
+        let resolving = self.inner.call(name);
+        let ip_preference = self.ip_preference;
+        let cache = self.cache.clone();
+
+        Box::pin(async move {
+            let addrs = ip_preference.apply(resolving.await?)?;
+
+            if let Some(cache) = cache {
+                cache.set(addrs.clone());
+            }
+
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+/// Drops the cached addresses of the resolver when a connection fails, so
+/// the next connection resolves the host name again instead of retrying an
+/// address that went away.
+#[derive(Debug, Clone)]
+pub(crate) struct CachedAddrConnector<C> {
+    inner: C,
+    cache: Option<AddrCache>,
+}
+
+impl<C> CachedAddrConnector<C> {
+    pub(crate) fn new(inner: C, cache: Option<AddrCache>) -> Self {
+        Self { inner, cache }
+    }
+}
+
+impl<C> Service<Uri> for CachedAddrConnector<C>
+where
+    C: Service<Uri>,
+    C::Future: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<C::Response, C::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), C::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let connecting = self.inner.call(uri);
+        let cache = self.cache.clone();
+
+        Box::pin(async move {
+            let result = connecting.await;
+
+            if let (Err(_), Some(cache)) = (&result, cache) {
+                cache.invalidate();
+            }
+
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hyper_util::client::legacy::connect::HttpConnector;
+    use std::future::{ready, Ready};
+    use std::net::IpAddr;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::net::TcpListener;
+
+    /// Resolves every name to the same addresses.
+    #[derive(Debug, Clone)]
+    struct StaticResolver(Vec<IpAddr>);
+
+    impl Service<Name> for StaticResolver {
+        type Response = vec::IntoIter<SocketAddr>;
+        type Error = io::Error;
+        type Future = Ready<io::Result<Self::Response>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Name) -> Self::Future {
+            let addrs: Vec<SocketAddr> = self.0.iter().map(|ip| SocketAddr::new(*ip, 0)).collect();
+            ready(Ok(addrs.into_iter()))
+        }
+    }
+
+    /// Resolves every name to the same address and counts the lookups.
+    #[derive(Debug, Clone)]
+    struct CountingResolver(SocketAddr, Arc<AtomicUsize>);
+
+    impl Service<Name> for CountingResolver {
+        type Response = vec::IntoIter<SocketAddr>;
+        type Error = io::Error;
+        type Future = Ready<io::Result<Self::Response>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Name) -> Self::Future {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            ready(Ok(vec![self.0].into_iter()))
+        }
+    }
+
+    async fn listening_addr() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+
+        addr
+    }
+
+    /// A connector with `cached` as the resolved address, resolving the
+    /// host name to `resolved` once it is stale.
+    fn cached_addr_connector(
+        cached: SocketAddr,
+        resolved: SocketAddr,
+        ttl: Duration,
+    ) -> (
+        CachedAddrConnector<HttpConnector<Resolver<CountingResolver>>>,
+        Arc<AtomicUsize>,
+    ) {
+        let lookups = Arc::new(AtomicUsize::new(0));
+        let resolver = Resolver {
+            cache: Some(AddrCache::new(cached, ttl)),
+            ..Resolver::with_inner(CountingResolver(resolved, lookups.clone()), IpPreference::DualStack)
+        };
+        let cache = resolver.cache();
+
+        (
+            CachedAddrConnector::new(HttpConnector::new_with_resolver(resolver), cache),
+            lookups,
+        )
+    }
+
+    fn addrs() -> Vec<SocketAddr> {
+        vec![
+            "[2001:db8::1]:443".parse().unwrap(),
+            "192.0.2.1:443".parse().unwrap(),
+            "[2001:db8::2]:443".parse().unwrap(),
+            "192.0.2.2:443".parse().unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_dual_stack_keeps_the_order() {
+        let ordered = IpPreference::DualStack.apply(addrs().into_iter()).unwrap();
+
+        assert_eq!(addrs(), ordered);
+    }
+
+    #[test]
+    fn test_prefer_ipv4_orders_ipv4_first() {
+        let ordered = IpPreference::PreferIpv4.apply(addrs().into_iter()).unwrap();
+        let ordered: Vec<String> = ordered.iter().map(ToString::to_string).collect();
+
+        assert_eq!(
+            vec![
+                "192.0.2.1:443",
+                "192.0.2.2:443",
+                "[2001:db8::1]:443",
+                "[2001:db8::2]:443"
+            ],
+            ordered
+        );
+    }
+
+    #[test]
+    fn test_ipv4_only_drops_ipv6() {
+        let ordered = IpPreference::Ipv4Only.apply(addrs().into_iter()).unwrap();
+
+        assert!(ordered.iter().all(SocketAddr::is_ipv4));
+        assert_eq!(2, ordered.len());
+    }
+
+    #[test]
+    fn test_ipv4_only_without_ipv4_addresses() {
+        let ipv6 = addrs().into_iter().filter(SocketAddr::is_ipv6);
+
+        assert!(IpPreference::Ipv4Only.apply(ipv6).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_dead_ipv6_falls_back_to_ipv4() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+
+        // 100::/64 is a discard-only prefix, connecting there never succeeds.
+        let stub = StaticResolver(vec!["100::1".parse().unwrap(), "127.0.0.1".parse().unwrap()]);
+        let mut connector = HttpConnector::new_with_resolver(Resolver::with_inner(stub, IpPreference::DualStack));
+
+        let uri: Uri = format!("http://api.push.apple.com:{}", addr.port()).parse().unwrap();
+        let stream = connector.call(uri).await.unwrap();
+
+        assert_eq!(addr, stream.inner().peer_addr().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_host_override_skips_the_resolver() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+
+        let stub = StaticResolver(vec!["100::1".parse().unwrap()]);
+        let resolver = Resolver {
+            host_override: Some(addr),
+            ..Resolver::with_inner(stub, IpPreference::Ipv4Only)
+        };
+        let mut connector = HttpConnector::new_with_resolver(resolver);
+
+        let stream = connector
+            .call(Uri::from_static("http://api.push.apple.com"))
+            .await
+            .unwrap();
+
+        assert_eq!(addr, stream.inner().peer_addr().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_resolved_addr_is_used_until_a_connection_fails() {
+        let resolved = listening_addr().await;
+        let stale = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+        let (mut connector, lookups) = cached_addr_connector(stale, resolved, Duration::from_secs(300));
+        let uri = Uri::from_static("http://api.push.apple.com");
+
+        assert!(connector.call(uri.clone()).await.is_err());
+        assert_eq!(0, lookups.load(Ordering::SeqCst));
+
+        let stream = connector.call(uri.clone()).await.unwrap();
+        assert_eq!(resolved, stream.inner().peer_addr().unwrap());
+        assert_eq!(1, lookups.load(Ordering::SeqCst));
+
+        connector.call(uri).await.unwrap();
+        assert_eq!(1, lookups.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_resolved_addr_skips_the_resolver_within_the_ttl() {
+        let cached = listening_addr().await;
+        let resolved = listening_addr().await;
+        let uri = Uri::from_static("http://api.push.apple.com");
+
+        let (mut connector, lookups) = cached_addr_connector(cached, resolved, Duration::from_secs(300));
+        let stream = connector.call(uri.clone()).await.unwrap();
+
+        assert_eq!(cached, stream.inner().peer_addr().unwrap());
+        assert_eq!(0, lookups.load(Ordering::SeqCst));
+
+        let (mut connector, lookups) = cached_addr_connector(cached, resolved, Duration::ZERO);
+        let stream = connector.call(uri).await.unwrap();
+
+        assert_eq!(resolved, stream.inner().peer_addr().unwrap());
+        assert_eq!(1, lookups.load(Ordering::SeqCst));
+    }
+}
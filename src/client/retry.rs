@@ -0,0 +1,463 @@
+//! Retrying sends that failed for a temporary reason
+
+use crate::error::Error;
+use crate::response::Response;
+use std::collections::hash_map::RandomState;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How [`Client::send_with_retry`](super::Client::send_with_retry) retries a
+/// send that failed with a [retryable](Error::is_retryable) error. The delay
+/// between the attempts doubles after every retry, and is then randomized
+/// by the [`Jitter`] of the policy.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// The maximum number of attempts, including the first one
+    pub max_attempts: u32,
+    /// The delay before the first retry
+    pub base_delay: Duration,
+    /// The upper limit for the delay between two attempts, also for a
+    /// `Retry-After` of the response. A send asked to wait longer fails
+    /// without retrying.
+    pub max_delay: Duration,
+    /// How the delays are randomized
+    pub jitter: Jitter,
+    /// The retries left to all sends using this policy and its clones
+    pub budget: Option<RetryBudget>,
+}
+
+/// A number of retries shared by many sends, e.g. all the sends of a batch,
+/// set with [`RetryPolicy::with_budget`]. Once the budget is used up, the
+/// sends fail with their first retryable error instead of retrying, so a
+/// temporary APNs outage doesn't multiply the load of a large batch by the
+/// attempts of the policy. Clones share the same budget.
+///
+/// ```rust
+/// # use a2::client::{RetryBudget, RetryPolicy};
+/// # fn main() {
+/// let budget = RetryBudget::new(100);
+/// let policy = RetryPolicy::new(3).with_budget(budget.clone());
+///
+/// assert_eq!(100, budget.remaining());
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryBudget(Arc<AtomicU32>);
+
+impl RetryBudget {
+    /// A budget of at most `max_retries` retries in total.
+    pub fn new(max_retries: u32) -> Self {
+        Self(Arc::new(AtomicU32::new(max_retries)))
+    }
+
+    /// The retries left in the budget.
+    pub fn remaining(&self) -> u32 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Takes a retry out of the budget, false if none is left.
+    fn try_take(&self) -> bool {
+        self.0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |remaining| {
+                remaining.checked_sub(1)
+            })
+            .is_ok()
+    }
+}
+
+/// How the delays of a [`RetryPolicy`] are randomized, so the clients that
+/// failed at the same time don't all retry at the same time, following the
+/// "Exponential Backoff And Jitter" article of the AWS Architecture Blog.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Jitter {
+    /// A random delay between zero and the backoff delay, spreading the
+    /// retries the most
+    #[default]
+    Full,
+    /// Half of the backoff delay plus a random delay up to the other half,
+    /// never retrying sooner than half the backoff delay
+    Equal,
+    /// The backoff delay as is, for fleets that coordinate their retries in
+    /// some other way
+    None,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            jitter: Jitter::default(),
+            budget: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy making at most `max_attempts` attempts with the default
+    /// delays.
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the delay before the first retry.
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    /// Sets the upper limit for the delay between two attempts.
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets how the delays are randomized, full jitter by default.
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Shares the retries of the budget between all sends with this policy,
+    /// on top of the maximum attempts of every send.
+    pub fn with_budget(mut self, budget: RetryBudget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Whether a `Retry-After` wait is short enough to retry after, at most
+    /// the maximum delay.
+    fn waits_for(&self, retry_after: Option<Duration>) -> bool {
+        retry_after.map_or(true, |wait| wait <= self.max_delay)
+    }
+
+    /// Whether another attempt may be made after the given failed attempt,
+    /// taking the retry out of the budget.
+    fn may_retry(&self, attempt: u32) -> bool {
+        attempt < self.max_attempts && self.budget.as_ref().map_or(true, RetryBudget::try_take)
+    }
+
+    /// The delay after the given failed attempt, starting from one, before
+    /// the jitter.
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.base_delay.saturating_mul(factor).min(self.max_delay)
+    }
+
+    /// The delay after the given failed attempt with the jitter applied.
+    fn jittered_delay(&self, attempt: u32, rng: &mut Rng) -> Duration {
+        let delay = self.delay(attempt);
+
+        match self.jitter {
+            Jitter::Full => rng.up_to(delay),
+            Jitter::Equal => delay / 2 + rng.up_to(delay - delay / 2),
+            Jitter::None => delay,
+        }
+    }
+}
+
+/// A small splitmix64 generator for the jitter and the generated apns-ids,
+/// which need values that differ between the clients rather than ones that
+/// can't be guessed.
+pub(crate) struct Rng(u64);
+
+impl Rng {
+    fn seeded(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    /// Seeded from the random keys the standard library generates for
+    /// `HashMap`s.
+    pub(crate) fn from_entropy() -> Self {
+        Self::seeded(RandomState::new().build_hasher().finish())
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A random duration between zero and `max`, both included.
+    fn up_to(&mut self, max: Duration) -> Duration {
+        let nanos = u64::try_from(max.as_nanos()).unwrap_or(u64::MAX);
+
+        match nanos.checked_add(1) {
+            Some(range) => Duration::from_nanos(self.next_u64() % range),
+            None => Duration::from_nanos(self.next_u64()),
+        }
+    }
+}
+
+/// Calls `send` until it succeeds, fails with an error that is not
+/// retryable or the attempts of the policy are used up. The returned
+/// response holds the number of attempts made.
+pub(crate) async fn retry<F, R>(policy: &RetryPolicy, mut send: F) -> Result<Response, Error>
+where
+    F: FnMut() -> R,
+    R: Future<Output = Result<Response, Error>>,
+{
+    let mut attempt = 1;
+    let mut rng = Rng::from_entropy();
+
+    loop {
+        match send().await {
+            Ok(mut response) => {
+                response.attempts = attempt;
+                return Ok(response);
+            }
+            Err(e) if e.is_retryable() && policy.waits_for(retry_after(&e)) && policy.may_retry(attempt) => {
+                #[cfg(feature = "tracing")]
+                {
+                    tracing::debug!("Retrying the failed attempt {}: {}", attempt, e);
+                }
+
+                let delay = policy.jittered_delay(attempt, &mut rng);
+
+                tokio::time::sleep(delay.max(retry_after(&e).unwrap_or_default())).await;
+                attempt += 1;
+            }
+            Err(Error::ResponseError(mut response)) => {
+                response.attempts = attempt;
+                return Err(Error::ResponseError(response));
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// How long the response of the error asks to wait before sending again.
+fn retry_after(error: &Error) -> Option<Duration> {
+    match error {
+        Error::ResponseError(response) => response.retry_after(),
+        _ => None,
+    }
+}
+
+/// The seconds to wait of a `Retry-After` header, given either in
+/// delta-seconds or as an HTTP-date relative to `now`. A date in the past
+/// means no wait.
+pub(crate) fn parse_retry_after(value: &str, now: SystemTime) -> Option<u32> {
+    let value = value.trim();
+
+    let secs = match value.parse::<u64>() {
+        Ok(secs) => secs,
+        Err(_) => {
+            let date = httpdate::parse_http_date(value).ok()?;
+            date.duration_since(now).unwrap_or_default().as_secs()
+        }
+    };
+
+    Some(u32::try_from(secs).unwrap_or(u32::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::ErrorReason;
+    use std::cell::Cell;
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::new(max_attempts).with_base_delay(Duration::from_millis(1))
+    }
+
+    #[test]
+    fn test_delays_double_up_to_the_maximum() {
+        let policy = RetryPolicy::default()
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_millis(300));
+
+        assert_eq!(Duration::from_millis(100), policy.delay(1));
+        assert_eq!(Duration::from_millis(200), policy.delay(2));
+        assert_eq!(Duration::from_millis(300), policy.delay(3));
+        assert_eq!(Duration::from_millis(300), policy.delay(40));
+    }
+
+    #[test]
+    fn test_jitter_keeps_the_delays_in_bounds() {
+        let policy = RetryPolicy::default()
+            .with_base_delay(Duration::from_millis(100))
+            .with_max_delay(Duration::from_secs(1));
+        let mut rng = Rng::seeded(42);
+
+        for attempt in 1..6 {
+            let delay = policy.delay(attempt);
+
+            for _ in 0..100 {
+                let full = policy
+                    .clone()
+                    .with_jitter(Jitter::Full)
+                    .jittered_delay(attempt, &mut rng);
+                assert!(full <= delay, "{:?} > {:?}", full, delay);
+
+                let equal = policy
+                    .clone()
+                    .with_jitter(Jitter::Equal)
+                    .jittered_delay(attempt, &mut rng);
+                assert!(
+                    equal >= delay / 2 && equal <= delay,
+                    "{:?} outside of {:?}",
+                    equal,
+                    delay
+                );
+
+                let none = policy
+                    .clone()
+                    .with_jitter(Jitter::None)
+                    .jittered_delay(attempt, &mut rng);
+                assert_eq!(delay, none);
+            }
+        }
+
+        let full = policy.with_jitter(Jitter::Full);
+        let delays: Vec<Duration> = (0..10).map(|_| full.jittered_delay(3, &mut rng)).collect();
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+    }
+
+    #[tokio::test]
+    async fn test_retry_counts_the_attempts() {
+        let calls = Cell::new(0);
+
+        let result = retry(&policy(5), || {
+            calls.set(calls.get() + 1);
+
+            async {
+                match calls.get() {
+                    1 => Err(Error::ResponseError(Box::new(Response::new(
+                        503,
+                        Some(ErrorReason::ServiceUnavailable),
+                    )))),
+                    2 => Err(Error::RequestTimeout(20)),
+                    _ => Ok(Response::new(200, None)),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(3, result.unwrap().attempts);
+        assert_eq!(3, calls.get());
+    }
+
+    #[tokio::test]
+    async fn test_retry_stops_at_the_maximum_attempts() {
+        let calls = Cell::new(0);
+
+        let result = retry(&policy(2), || {
+            calls.set(calls.get() + 1);
+            async {
+                Err(Error::ResponseError(Box::new(Response::new(
+                    500,
+                    Some(ErrorReason::InternalServerError),
+                ))))
+            }
+        })
+        .await;
+
+        let Err(Error::ResponseError(response)) = result else {
+            panic!("unexpected result {:?}", result);
+        };
+        assert_eq!(2, response.attempts);
+        assert_eq!(2, calls.get());
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_caps_the_retries_of_a_batch() {
+        let budget = RetryBudget::new(2);
+        let policy = policy(5).with_budget(budget.clone());
+        let calls = Cell::new(0);
+        let mut attempts = Vec::new();
+
+        for _ in 0..3 {
+            let result = retry(&policy, || {
+                calls.set(calls.get() + 1);
+                async {
+                    Err(Error::ResponseError(Box::new(Response::new(
+                        503,
+                        Some(ErrorReason::ServiceUnavailable),
+                    ))))
+                }
+            })
+            .await;
+
+            let Err(Error::ResponseError(response)) = result else {
+                panic!("unexpected result {:?}", result);
+            };
+            attempts.push(response.attempts);
+        }
+
+        assert_eq!(vec![3, 1, 1], attempts);
+        assert_eq!(5, calls.get());
+        assert_eq!(0, budget.remaining());
+    }
+
+    #[tokio::test]
+    async fn test_retry_does_not_retry_permanent_errors() {
+        let calls = Cell::new(0);
+
+        let result = retry(&policy(5), || {
+            calls.set(calls.get() + 1);
+            async {
+                Err(Error::ResponseError(Box::new(Response::new(
+                    400,
+                    Some(ErrorReason::BadDeviceToken),
+                ))))
+            }
+        })
+        .await;
+
+        assert!(matches!(
+            result,
+            Err(Error::ResponseError(ref response)) if response.attempts == 1
+        ));
+        assert_eq!(1, calls.get());
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_over_the_maximum_delay_is_not_waited_for() {
+        let budget = RetryBudget::new(5);
+        let policy = policy(5).with_budget(budget.clone());
+        let calls = Cell::new(0);
+
+        let result = retry(&policy, || {
+            calls.set(calls.get() + 1);
+
+            let mut response = Response::new(429, Some(ErrorReason::TooManyRequests));
+            response.retry_after_secs = Some(u32::MAX);
+
+            async { Err(Error::ResponseError(Box::new(response))) }
+        })
+        .await;
+
+        let Err(Error::ResponseError(response)) = result else {
+            panic!("unexpected result {:?}", result);
+        };
+        assert_eq!(1, response.attempts);
+        assert_eq!(Some(Duration::from_secs(u32::MAX.into())), response.retry_after());
+        assert_eq!(1, calls.get());
+        assert_eq!(5, budget.remaining());
+    }
+
+    #[test]
+    fn test_retry_after_in_seconds() {
+        assert_eq!(Some(120), parse_retry_after(" 120", SystemTime::now()));
+        assert_eq!(None, parse_retry_after("soon", SystemTime::now()));
+    }
+
+    #[test]
+    fn test_retry_after_as_an_http_date() {
+        let now = httpdate::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+
+        assert_eq!(Some(90), parse_retry_after("Sun, 06 Nov 1994 08:51:07 GMT", now));
+        assert_eq!(Some(0), parse_retry_after("Sun, 06 Nov 1994 08:00:00 GMT", now));
+    }
+}
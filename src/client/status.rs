@@ -0,0 +1,76 @@
+//! Tracking whether the connection to APNs is usable
+
+use crate::error::Error;
+use parking_lot::Mutex;
+use std::sync::Arc;
+
+/// Whether the client can currently reach APNs, as seen from the outcome of
+/// the latest request. Returned from [`Client::status`](super::Client::status)
+/// for health checks.
+///
+/// A response from APNs counts as connected even if it rejects the
+/// notification, only failing to get a response at all marks the client as
+/// failing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClientStatus {
+    /// No request has completed yet
+    Disconnected,
+    /// The latest request got a response from APNs
+    Connected,
+    /// The latest request got no response from APNs
+    Failing {
+        /// The error of the latest request
+        last_error: String,
+    },
+}
+
+/// The status shared between the clones of a client.
+#[derive(Debug, Clone)]
+pub(crate) struct StatusTracker(Arc<Mutex<ClientStatus>>);
+
+impl StatusTracker {
+    pub(crate) fn new() -> Self {
+        Self(Arc::new(Mutex::new(ClientStatus::Disconnected)))
+    }
+
+    pub(crate) fn get(&self) -> ClientStatus {
+        self.0.lock().clone()
+    }
+
+    /// Updates the status from the outcome of a request.
+    pub(crate) fn record<T>(&self, result: &Result<T, Error>) {
+        *self.0.lock() = match result {
+            Ok(_) => ClientStatus::Connected,
+            Err(e) => ClientStatus::Failing {
+                last_error: e.to_string(),
+            },
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_transitions() {
+        let tracker = StatusTracker::new();
+        let clone = tracker.clone();
+
+        assert_eq!(ClientStatus::Disconnected, tracker.get());
+
+        tracker.record(&Ok(()));
+        assert_eq!(ClientStatus::Connected, clone.get());
+
+        tracker.record::<()>(&Err(Error::RequestTimeout(20)));
+        assert_eq!(
+            ClientStatus::Failing {
+                last_error: String::from("The request timed out after 20 s")
+            },
+            clone.get()
+        );
+
+        tracker.record(&Ok(()));
+        assert_eq!(ClientStatus::Connected, clone.get());
+    }
+}
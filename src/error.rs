@@ -1,69 +1,265 @@
 /// Error and result module
-use crate::{response::Response, signer::SignerError};
+#[cfg(feature = "client")]
+use crate::client::Endpoint;
+use crate::response::Response;
+#[cfg(feature = "client")]
+use crate::signer::SignerError;
 use std::io;
 use thiserror::Error;
 
+/// The errors of the crate. The `serde_json`, `std::io` and TLS errors
+/// convert into it with `From`, so `?` works for them in code returning it.
 #[derive(Debug, Error)]
 pub enum Error {
     /// User request or Apple response JSON data was faulty.
     #[error("Error serializing to JSON: {0}")]
     SerializeError(#[from] serde_json::Error),
 
+    #[cfg(feature = "client")]
     /// A problem connecting to APNs servers.
     #[error("Error connecting to APNs: {0}")]
     ConnectionError(#[from] hyper::Error),
 
+    #[cfg(feature = "client")]
     #[error("Http client error: {0}")]
     ClientError(#[from] hyper_util::client::legacy::Error),
 
+    #[cfg(feature = "client")]
     /// Couldn't generate an APNs token with the given key.
     #[error("Error creating a signature: {0}")]
     SignerError(#[from] SignerError),
 
     /// APNs couldn't accept the notification. Contains
     /// [Response](response/struct.Response.html) with additional
-    /// information.
+    /// information, boxed to keep the error small.
     #[error(
-        "Notification was not accepted by APNs (reason: {})",
+        "Notification was not accepted by APNs (reason: {}){}",
         .0.error
             .as_ref()
             .map(|e| e.reason.to_string())
-            .unwrap_or_else(|| "Unknown".to_string())
+            .unwrap_or_else(|| format!("Unknown, HTTP status {}", .0.code)),
+        .0.hint
+            .as_ref()
+            .map(|hint| format!(" {}", hint))
+            .unwrap_or_default()
     )]
-    ResponseError(Response),
+    ResponseError(Box<Response>),
 
     /// Invalid option values given in
     /// [NotificationOptions](request/notification/struct.NotificationOptions.html)
     #[error("Invalid options for APNs payload: {0}")]
     InvalidOptions(String),
 
+    /// The payload content is inconsistent in a way APNs or the device would
+    /// not handle as intended.
+    #[error("Invalid APNs payload: {0}")]
+    InvalidPayload(String),
+
+    /// The serialized payload is over the size limit APNs accepts, even after
+    /// removing all droppable custom data.
+    #[error("The payload is too large: {size} bytes, the maximum is {limit} bytes")]
+    PayloadTooLarge { size: usize, limit: usize },
+
     /// Error reading the certificate or private key.
     #[error("Error in reading a certificate file: {0}")]
     ReadError(#[from] io::Error),
 
+    #[cfg(feature = "client")]
     #[error("Error building TLS config: {0}")]
     Tls(#[from] rustls::Error),
 
+    #[cfg(feature = "client")]
     /// Error while creating the HTTP request
     #[error("Failed to construct HTTP request: {0}")]
     BuildRequestError(#[source] http::Error),
 
+    #[cfg(feature = "client")]
+    /// The response body was bigger than the configured maximum amount of
+    /// bytes.
+    #[error("The response body exceeded the limit of {0} bytes")]
+    ResponseTooLarge(usize),
+
+    #[cfg(feature = "client")]
     /// No repsonse from APNs after the given amount of time
     #[error("The request timed out after {0} s")]
     RequestTimeout(u64),
 
+    /// The TLS connection to APNs was set up without HTTP/2, which APNs
+    /// requires. Usually caused by a proxy that only speaks HTTP/1.1.
+    #[cfg(feature = "client")]
+    #[error(
+        "APNs requires HTTP/2, but the connection did not negotiate it. Check for a proxy downgrading it to HTTP/1.1"
+    )]
+    Http2Required,
+
+    /// The deadline given for the send passed before APNs responded.
+    #[cfg(feature = "client")]
+    #[error("The deadline of the send passed")]
+    DeadlineExceeded,
+
+    /// The client sends to another environment than the one asserted with
+    /// [`Client::assert_environment`](crate::client::Client::assert_environment).
+    #[cfg(feature = "client")]
+    #[error("Expected a client sending to {expected}, but it sends to {actual}")]
+    WrongEnvironment { expected: Endpoint, actual: Endpoint },
+
     /// Unexpected private key (only EC keys are supported).
     #[cfg(all(not(feature = "openssl"), feature = "ring"))]
     #[error("Unexpected private key: {0}")]
     UnexpectedKey(#[from] ring::error::KeyRejected),
 
+    #[cfg(feature = "client")]
     #[error("Invalid certificate")]
     InvalidCertificate,
 }
 
+impl Error {
+    /// Whether sending the same notification again can succeed: true for
+    /// connection problems, timeouts and the APNs errors that are
+    /// [retryable](crate::ErrorReason::is_retryable). An error response
+    /// without a reason is retryable for the statuses 429, 500 and 503.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            #[cfg(feature = "client")]
+            Error::ConnectionError(_) | Error::ClientError(_) | Error::RequestTimeout(_) => true,
+            Error::ResponseError(response) => match response.error {
+                Some(ref error) => error.reason.is_retryable(),
+                None => matches!(response.code, 429 | 500 | 503),
+            },
+            _ => false,
+        }
+    }
+
+    /// The status for an HTTP API sending the notification on behalf of its
+    /// caller to answer with: 400 for an invalid device token or
+    /// notification, 410 for an inactive device token, 413 for a payload
+    /// over the size limit, 429 for throttling, 500 for a broken key or
+    /// certificate, 504 for a timeout and 502 for other problems with APNs,
+    /// including its server errors and rejected credentials.
+    pub fn suggested_http_status(&self) -> u16 {
+        match self {
+            Error::ResponseError(response) => match response.error {
+                Some(ref error) => error.reason.suggested_http_status(),
+                None => match response.code {
+                    400 | 410 | 413 | 429 => response.code,
+                    _ => 502,
+                },
+            },
+            Error::SerializeError(_) | Error::InvalidOptions(_) | Error::InvalidPayload(_) => 400,
+            Error::PayloadTooLarge { .. } => 413,
+            #[cfg(feature = "client")]
+            Error::RequestTimeout(_) | Error::DeadlineExceeded => 504,
+            #[cfg(feature = "client")]
+            Error::ConnectionError(_) | Error::ClientError(_) | Error::ResponseTooLarge(_) | Error::Http2Required => {
+                502
+            }
+            _ => 500,
+        }
+    }
+}
+
 #[cfg(feature = "openssl")]
 impl From<openssl::error::ErrorStack> for Error {
     fn from(e: openssl::error::ErrorStack) -> Self {
         Self::SignerError(SignerError::OpenSSL(e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::ErrorReason;
+    use std::collections::HashMap;
+
+    fn serialize_key_map() -> Result<String, Error> {
+        let mut map = HashMap::new();
+        map.insert(vec![1, 2], "not a string key");
+
+        Ok(serde_json::to_string(&map)?)
+    }
+
+    fn read_missing_file() -> Result<Vec<u8>, Error> {
+        Ok(std::fs::read("this/file/does/not/exist")?)
+    }
+
+    #[test]
+    fn test_question_mark_conversions() {
+        assert!(matches!(serialize_key_map(), Err(Error::SerializeError(_))));
+        assert!(matches!(read_missing_file(), Err(Error::ReadError(_))));
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_tls_error_conversion() {
+        fn fail() -> Result<(), Error> {
+            Err(rustls::Error::NoCertificatesPresented)?
+        }
+
+        assert!(matches!(
+            fail(),
+            Err(Error::Tls(rustls::Error::NoCertificatesPresented))
+        ));
+    }
+
+    fn response_error(code: u16, reason: Option<ErrorReason>) -> Error {
+        Error::ResponseError(Box::new(Response::new(code, reason)))
+    }
+
+    #[test]
+    fn test_retryable_errors() {
+        let table = [
+            (response_error(400, Some(ErrorReason::IdleTimeout)), true),
+            (response_error(429, Some(ErrorReason::TooManyRequests)), true),
+            (response_error(503, Some(ErrorReason::Shutdown)), true),
+            (response_error(400, Some(ErrorReason::BadMessageId)), false),
+            (response_error(410, Some(ErrorReason::Unregistered)), false),
+            (response_error(503, None), true),
+            (response_error(502, None), false),
+            (Error::InvalidPayload(String::from("conflict")), false),
+        ];
+
+        for (error, retryable) in table {
+            assert_eq!(retryable, error.is_retryable(), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_suggested_http_status() {
+        let table = [
+            (response_error(400, Some(ErrorReason::BadDeviceToken)), 400),
+            (response_error(400, Some(ErrorReason::DeviceTokenNotForTopic)), 400),
+            (response_error(410, Some(ErrorReason::Unregistered)), 410),
+            (response_error(413, Some(ErrorReason::PayloadTooLarge)), 413),
+            (response_error(429, Some(ErrorReason::TooManyRequests)), 429),
+            (response_error(403, Some(ErrorReason::InvalidProviderToken)), 502),
+            (response_error(400, Some(ErrorReason::BadTopic)), 502),
+            (response_error(500, Some(ErrorReason::InternalServerError)), 502),
+            (response_error(503, Some(ErrorReason::ServiceUnavailable)), 502),
+            (response_error(410, None), 410),
+            (response_error(502, None), 502),
+            (Error::InvalidPayload(String::from("conflict")), 400),
+            (
+                Error::PayloadTooLarge {
+                    size: 5000,
+                    limit: 4096,
+                },
+                413,
+            ),
+            (read_missing_file().unwrap_err(), 500),
+        ];
+
+        for (error, status) in table {
+            assert_eq!(status, error.suggested_http_status(), "{:?}", error);
+        }
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_suggested_http_status_of_client_errors() {
+        assert_eq!(504, Error::RequestTimeout(20).suggested_http_status());
+        assert_eq!(504, Error::DeadlineExceeded.suggested_http_status());
+        assert_eq!(502, Error::Http2Required.suggested_http_status());
+        assert_eq!(502, Error::ResponseTooLarge(1024).suggested_http_status());
+        assert_eq!(500, Error::InvalidCertificate.suggested_http_status());
+    }
+}
@@ -20,7 +20,9 @@
 //! section](request/payload/struct.Payload.html#method.add_custom_data),
 //! defined by a selected root key. Any data using `#[derive(Serialize)]` from
 //! [Serde](https://serde.rs/) works, allowing usage of type-safe structs or
-//! dynamic hashmaps to generate the custom data.
+//! dynamic hashmaps to generate the custom data. The custom data keys are
+//! serialized sorted, or in the order they were added with the
+//! `preserve_order` feature.
 //!
 //! ## Client
 //!
@@ -28,17 +30,35 @@
 //! [certificate](client/struct.Client.html#method.certificate) or
 //! [token](client/struct.Client.html#method.token) authentication.
 //!
+//! The client is behind the `client` feature, enabled by both of the TLS
+//! features `openssl` (default) and `ring`. Building with
+//! `default-features = false` leaves out the HTTP and TLS dependencies and
+//! only provides the payload builders and response types, for sending the
+//! notifications over an existing networking layer.
+//!
+//! The `unstable` feature adds
+//! [`Client::send_raw_stream`](client/struct.Client.html#method.send_raw_stream),
+//! which may change or go away in any release.
+//!
+//! The `rayon` feature adds
+//! [`build_json_parallel`](request/notification/fn.build_json_parallel.html)
+//! to serialize the payloads of a large batch on multiple threads.
+//!
 //! ## Example sending a plain notification using token authentication:
 //!
 //! ```no_run
-//! # use a2::{DefaultNotificationBuilder, NotificationBuilder, Client, ClientConfig, Endpoint};
+//! # #[cfg(feature = "client")]
+//! # use a2::{CategoryId, DefaultNotificationBuilder, NotificationBuilder, Client, ClientConfig, Endpoint};
 //! # use std::fs::File;
+//! # #[cfg(not(feature = "client"))]
+//! # fn main() {}
+//! # #[cfg(feature = "client")]
 //! # #[tokio::main]
 //! # async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 //! let mut builder = DefaultNotificationBuilder::new()
 //!     .set_body("Hi there")
 //!     .set_badge(420)
-//!     .set_category("cat1")
+//!     .set_category(CategoryId::new("cat1")?)
 //!     .set_sound("ping.flac");
 //!
 //! let payload = builder.build("device-token-from-the-user", Default::default());
@@ -83,7 +103,7 @@
 //!     };
 //!
 //!     let mut payload = DefaultNotificationBuilder::new()
-//!         .set_content_available()
+//!         .set_content_available(true)
 //!         .build("device-token-from-the-user",
 //!         NotificationOptions {
 //!             apns_priority: Some(Priority::Normal),
@@ -108,8 +128,8 @@
 //! ```
 #![warn(clippy::unwrap_used)]
 
-#[cfg(not(any(feature = "openssl", feature = "ring")))]
-compile_error!("either feature \"openssl\" or feature \"ring\" has to be enabled");
+#[cfg(all(feature = "client", not(any(feature = "openssl", feature = "ring"))))]
+compile_error!("the \"client\" feature needs either feature \"openssl\" or feature \"ring\" to be enabled");
 
 #[macro_use]
 extern crate serde;
@@ -118,19 +138,30 @@ extern crate serde;
 #[macro_use]
 extern crate serde_json;
 
+#[cfg(feature = "client")]
 pub mod client;
+pub mod clock;
 pub mod error;
 pub mod request;
 pub mod response;
+#[cfg(feature = "client")]
 mod signer;
 
 pub use crate::request::notification::{
-    CollapseId, DefaultNotificationBuilder, NotificationBuilder, NotificationOptions, Priority, PushType,
-    WebNotificationBuilder, WebPushAlert,
+    CategoryId, CollapseId, DefaultNotificationBuilder, InterruptionLevel, LiveActivityEvent,
+    LiveActivityNotificationBuilder, MdmNotificationBuilder, NotificationBuilder, NotificationOptions, Priority,
+    PushToTalkNotificationBuilder, PushType, WebNotificationBuilder, WebPushAlert,
 };
 
 pub use crate::response::{ErrorBody, ErrorReason, Response};
 
-pub use crate::client::{Client, ClientConfig, Endpoint};
+#[cfg(feature = "client")]
+pub use crate::response::RawResponse;
+
+#[cfg(feature = "client")]
+pub use crate::client::{
+    Client, ClientConfig, ClientStatus, Endpoint, FlushStrategy, IpPreference, PushTypeDefaults, RetryPolicy,
+    SendOptions,
+};
 
 pub use crate::error::Error;
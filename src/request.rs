@@ -1,4 +1,6 @@
 //! The request payload module
 
+pub mod ndjson;
 pub mod notification;
 pub mod payload;
+pub mod validation;
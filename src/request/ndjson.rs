@@ -0,0 +1,144 @@
+//! Reading payloads from newline-delimited JSON, e.g. in a relay
+
+use crate::error::Error;
+use crate::request::payload::Payload;
+use serde::Deserialize;
+use std::io::{self, BufRead, BufReader, Lines};
+use thiserror::Error;
+
+/// A line of the input: the device token and the payload sent to it.
+#[derive(Deserialize)]
+struct Envelope {
+    device_token: String,
+    payload: Payload<'static>,
+}
+
+/// A line that could not be read or parsed, reported by [`read_payloads`].
+#[derive(Debug, Error)]
+#[error("Line {line}: {error}")]
+pub struct LineError {
+    /// The number of the line, starting at 1
+    pub line: usize,
+    /// What went wrong on the line
+    #[source]
+    pub error: Error,
+}
+
+/// An iterator over the payloads of newline-delimited JSON, created by
+/// [`read_payloads`].
+#[derive(Debug)]
+pub struct PayloadLines<R> {
+    lines: Lines<BufReader<R>>,
+    line: usize,
+    failed: bool,
+}
+
+/// Reads newline-delimited JSON with one
+/// `{"device_token": ..., "payload": {...}}` envelope per line, yielding
+/// the payloads with their device token set, ready for
+/// [`Client::send_stream`](crate::client::Client::send_stream). The
+/// payloads have default options.
+///
+/// Blank lines are skipped. A line that isn't a valid envelope is reported
+/// with its line number and the lines after it are still read, only an
+/// error reading the input ends the iterator.
+///
+/// ```rust
+/// # use a2::request::ndjson::read_payloads;
+/// # use a2::request::payload::PayloadLike;
+/// # fn main() {
+/// let input = r#"{"device_token": "token", "payload": {"aps": {"alert": "Hi"}}}"#;
+/// let payloads: Vec<_> = read_payloads(input.as_bytes()).collect();
+///
+/// assert_eq!("token", payloads[0].as_ref().unwrap().get_device_token());
+/// # }
+/// ```
+pub fn read_payloads<R: io::Read>(reader: R) -> PayloadLines<R> {
+    PayloadLines {
+        lines: BufReader::new(reader).lines(),
+        line: 0,
+        failed: false,
+    }
+}
+
+impl<R: io::Read> Iterator for PayloadLines<R> {
+    type Item = Result<Payload<'static>, LineError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Reading on after an error of the reader may never make progress.
+        if self.failed {
+            return None;
+        }
+
+        loop {
+            self.line += 1;
+
+            let text = match self.lines.next()? {
+                Ok(text) => text,
+                Err(e) => {
+                    self.failed = true;
+                    return Some(Err(self.error(e.into())));
+                }
+            };
+
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            return Some(
+                serde_json::from_str::<Envelope>(&text)
+                    .map(|envelope| {
+                        let mut payload = envelope.payload;
+                        payload.device_token = envelope.device_token.into();
+                        payload
+                    })
+                    .map_err(|e| self.error(e.into())),
+            );
+        }
+    }
+}
+
+impl<R> PayloadLines<R> {
+    fn error(&self, error: Error) -> LineError {
+        LineError { line: self.line, error }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::payload::PayloadLike;
+
+    #[test]
+    fn test_payloads_with_a_malformed_line() {
+        let input = [
+            r#"{"device_token": "a", "payload": {"aps": {"alert": "Hi"}, "chat": 1}}"#,
+            "",
+            r#"{"device_token": "b", "payload": {"aps": {"alert": "#,
+            "   ",
+            r#"{"device_token": "c", "payload": {"aps": {"badge": 2}}}"#,
+        ]
+        .join("\n");
+
+        let mut payloads = read_payloads(input.as_bytes());
+
+        let first = payloads.next().unwrap().unwrap();
+        assert_eq!("a", first.get_device_token());
+        assert_eq!(r#"{"aps":{"alert":"Hi"},"chat":1}"#, first.to_json_string().unwrap());
+
+        let error = payloads.next().unwrap().unwrap_err();
+        assert_eq!(3, error.line);
+        assert!(matches!(error.error, Error::SerializeError(_)));
+
+        let last = payloads.next().unwrap().unwrap();
+        assert_eq!("c", last.get_device_token());
+        assert_eq!(Some(2), last.aps.badge);
+
+        assert!(payloads.next().is_none());
+    }
+
+    #[test]
+    fn test_payloads_of_empty_input() {
+        assert_eq!(0, read_payloads("\n\n".as_bytes()).count());
+    }
+}
@@ -1,16 +1,175 @@
 /// The `aps` notification content builders
 mod default;
+mod live_activity;
+mod mdm;
 mod options;
+mod push_to_talk;
 mod web;
 
-use std::borrow::Cow;
-pub use self::default::{DefaultAlert, DefaultNotificationBuilder, DefaultSound};
+pub use self::default::{CategoryId, DefaultAlert, DefaultNotificationBuilder, DefaultSound, InterruptionLevel};
+pub use self::live_activity::{LiveActivityEvent, LiveActivityNotificationBuilder};
+pub use self::mdm::MdmNotificationBuilder;
 pub use self::options::{CollapseId, NotificationOptions, Priority, PushType};
+pub use self::push_to_talk::PushToTalkNotificationBuilder;
 pub use self::web::{WebNotificationBuilder, WebPushAlert};
+use std::borrow::Cow;
 
-use crate::request::payload::Payload;
+use crate::error::Error;
+use crate::request::payload::{self, Payload, PayloadLike};
 
 pub trait NotificationBuilder<'a> {
     /// Generates the request payload to be send with the `Client`.
     fn build(self, device_token: impl Into<Cow<'a, str>>, options: NotificationOptions<'a>) -> Payload<'a>;
+
+    /// Generates the request payload like [`build`](Self::build), returning
+    /// [`PayloadTooLarge`](Error::PayloadTooLarge) if it's over the size
+    /// limit APNs accepts and [`InvalidPayload`](Error::InvalidPayload) if
+    /// it doesn't [validate](PayloadLike::validate).
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .try_build("token", Default::default());
+    /// assert!(payload.is_ok());
+    ///
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a".repeat(5000))
+    ///     .try_build("token", Default::default());
+    /// assert!(payload.is_err());
+    /// # }
+    /// ```
+    fn try_build(
+        self,
+        device_token: impl Into<Cow<'a, str>>,
+        options: NotificationOptions<'a>,
+    ) -> Result<Payload<'a>, Error>
+    where
+        Self: Sized,
+    {
+        let payload = self.build(device_token, options);
+        let size = payload.to_json_string()?.len();
+        let limit = payload::max_payload_size(&payload.options);
+
+        if size > limit {
+            return Err(Error::PayloadTooLarge { size, limit });
+        }
+
+        payload.validate()?;
+
+        Ok(payload)
+    }
+}
+
+/// Builds and serializes a payload for every builder and device token on
+/// the rayon thread pool, for preparing a large campaign ahead of sending
+/// it. The results are in the order of `items`, each either the JSON bytes
+/// of the payload or the error [`try_build`](NotificationBuilder::try_build)
+/// returns for it.
+///
+/// ```rust
+/// # use a2::request::notification::{build_json_parallel, DefaultNotificationBuilder};
+/// # fn main() {
+/// let builder = DefaultNotificationBuilder::new().set_body("a body");
+/// let items = vec![(builder.clone(), "token-1"), (builder, "token-2")];
+/// let results = build_json_parallel(&items, &Default::default());
+///
+/// assert_eq!(2, results.len());
+/// assert!(results.iter().all(Result::is_ok));
+/// # }
+/// ```
+#[cfg(feature = "rayon")]
+pub fn build_json_parallel<'a, B>(
+    items: &[(B, &'a str)],
+    options: &NotificationOptions<'a>,
+) -> Vec<Result<Vec<u8>, Error>>
+where
+    B: NotificationBuilder<'a> + Clone + Sync,
+{
+    use rayon::prelude::*;
+
+    items
+        .par_iter()
+        .map(|(builder, device_token)| {
+            let payload = builder.clone().try_build(*device_token, options.clone())?;
+
+            Ok(payload.to_json_string()?.into_bytes())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_build_refuses_an_oversized_payload() {
+        let body = "a".repeat(5000);
+        let builder = DefaultNotificationBuilder::new().set_body(&body);
+
+        let result = builder.clone().try_build("device-token", Default::default());
+        assert!(matches!(result, Err(Error::PayloadTooLarge { limit: 4096, .. })));
+
+        let payload = builder.build("device-token", Default::default());
+        assert!(payload.to_json_string().unwrap().len() > 4096);
+    }
+
+    #[test]
+    fn test_try_build_validates_the_payload() {
+        let result = DefaultNotificationBuilder::new()
+            .set_critical(true, None)
+            .set_interruption_level(InterruptionLevel::Passive)
+            .try_build("device-token", Default::default());
+
+        assert!(matches!(result, Err(Error::InvalidPayload(_))));
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_parallel_json_matches_sequential() {
+        let tokens: Vec<String> = (0..500).map(|i| format!("token-{}", i)).collect();
+        let items: Vec<(DefaultNotificationBuilder, &str)> = tokens
+            .iter()
+            .enumerate()
+            .map(|(i, token)| {
+                let builder = match i % 3 {
+                    0 => DefaultNotificationBuilder::new().set_body("a body").set_badge(i as u32),
+                    1 => DefaultNotificationBuilder::new()
+                        .set_title("a title")
+                        .set_sound("ping.aiff"),
+                    _ => DefaultNotificationBuilder::new()
+                        .set_critical(true, None)
+                        .set_interruption_level(InterruptionLevel::Passive),
+                };
+
+                (builder, token.as_str())
+            })
+            .collect();
+        let options = NotificationOptions {
+            apns_topic: Some("com.example.app".into()),
+            ..Default::default()
+        };
+
+        let parallel = build_json_parallel(&items, &options);
+        let sequential: Vec<Result<Vec<u8>, Error>> = items
+            .iter()
+            .map(|(builder, token)| {
+                let payload = builder.clone().try_build(*token, options.clone())?;
+
+                Ok(payload.to_json_string()?.into_bytes())
+            })
+            .collect();
+
+        assert_eq!(items.len(), parallel.len());
+
+        for (parallel, sequential) in parallel.iter().zip(&sequential) {
+            match (parallel, sequential) {
+                (Ok(parallel), Ok(sequential)) => assert_eq!(sequential, parallel),
+                (Err(parallel), Err(sequential)) => assert_eq!(sequential.to_string(), parallel.to_string()),
+                _ => panic!("the parallel result differs from the sequential one"),
+            }
+        }
+        assert!(matches!(parallel[2], Err(Error::InvalidPayload(_))));
+    }
 }
@@ -1,7 +1,14 @@
-use crate::request::notification::{NotificationBuilder, NotificationOptions};
-use crate::request::payload::{APSAlert, APSSound, Payload, APS};
-
-use std::{borrow::Cow, collections::BTreeMap};
+use crate::error::Error;
+use crate::request::notification::{NotificationBuilder, NotificationOptions, WebPushAlert};
+use crate::request::payload::{APSAlert, APSSound, CustomData, KeyCase, Payload, APS};
+
+use serde_json::Value;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt,
+    hash::{Hash, Hasher},
+};
 
 /// Represents a bool that serializes as a u8 0/1 for false/true respectively
 mod bool_as_u8 {
@@ -36,6 +43,13 @@ mod bool_as_u8 {
     }
 }
 
+/// The sound dictionary of a critical alert.
+///
+/// APNs only reads the `volume` of a sound for critical alerts, a regular
+/// notification always plays its sound at the volume the user set, so there
+/// is no dictionary form for a non-critical sound with a volume. The builder
+/// drops the volume when the notification is not critical and serializes a
+/// regular sound as just its name.
 #[derive(Deserialize, Serialize, Debug, Clone)]
 #[serde(rename_all = "kebab-case")]
 pub struct DefaultSound<'a> {
@@ -49,7 +63,114 @@ pub struct DefaultSound<'a> {
     volume: Option<f64>,
 }
 
-#[derive(Deserialize, Serialize, Debug, Clone)]
+// Volumes are compared bit for bit, so that equal sounds also hash the same.
+impl<'a> PartialEq for DefaultSound<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.critical == other.critical
+            && self.name == other.name
+            && self.volume.map(f64::to_bits) == other.volume.map(f64::to_bits)
+    }
+}
+
+impl<'a> Eq for DefaultSound<'a> {}
+
+impl<'a> Hash for DefaultSound<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.critical.hash(state);
+        self.name.hash(state);
+        self.volume.map(f64::to_bits).hash(state);
+    }
+}
+
+impl<'a> DefaultSound<'a> {
+    pub(crate) fn is_critical(&self) -> bool {
+        self.critical
+    }
+}
+
+/// How strongly a notification interrupts the user (iOS 15+).
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum InterruptionLevel {
+    /// Added to the notification list without lighting up the screen or
+    /// playing a sound
+    Passive,
+    /// Lights up the screen and may play a sound, the default
+    Active,
+    /// Breaks through Focus modes, needs the Time Sensitive entitlement
+    TimeSensitive,
+    /// Breaks through Focus modes and the mute switch, needs the critical
+    /// alerts entitlement
+    Critical,
+}
+
+/// The identifier of a notification category, matching the identifier of a
+/// `UNNotificationCategory` registered by the app to show its actions.
+///
+/// Defining the categories of an app once as `CategoryId`s keeps a typo in
+/// one of the notifications from silently dropping its action buttons. A
+/// `CategoryId` is never empty, as an empty identifier matches no category.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CategoryId<'a> {
+    value: Cow<'a, str>,
+}
+
+impl<'a> CategoryId<'a> {
+    /// A category identifier, failing with
+    /// [`InvalidOptions`](Error::InvalidOptions) for an empty one.
+    pub fn new(value: impl Into<Cow<'a, str>>) -> Result<CategoryId<'a>, Error> {
+        let value = value.into();
+
+        if value.is_empty() {
+            Err(Error::InvalidOptions(String::from("The category identifier is empty.")))
+        } else {
+            Ok(CategoryId { value })
+        }
+    }
+
+    /// The category identifier as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+}
+
+impl<'a> fmt::Display for CategoryId<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for CategoryId<'a> {
+    type Error = Error;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        CategoryId::new(value)
+    }
+}
+
+impl TryFrom<String> for CategoryId<'static> {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        CategoryId::new(value)
+    }
+}
+
+impl<'a> TryFrom<Cow<'a, str>> for CategoryId<'a> {
+    type Error = Error;
+
+    fn try_from(value: Cow<'a, str>) -> Result<Self, Self::Error> {
+        CategoryId::new(value)
+    }
+}
+
+impl<'a> From<CategoryId<'a>> for Cow<'a, str> {
+    fn from(category: CategoryId<'a>) -> Self {
+        category.value
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub struct DefaultAlert<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -81,47 +202,105 @@ pub struct DefaultAlert<'a> {
 }
 
 impl<'a> DefaultAlert<'a> {
+    /// True if none of the alert keys is set.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.subtitle.is_none()
+            && self.body.is_none()
+            && self.title_loc_key.is_none()
+            && self.title_loc_args.is_none()
+            && self.action_loc_key.is_none()
+            && self.loc_key.is_none()
+            && self.loc_args.is_none()
+            && self.launch_image.is_none()
+    }
+
     /// Converts all `Cow`s to owned data
     pub fn to_static(&self) -> DefaultAlert<'static> {
         DefaultAlert {
-            title: self.title.clone()
-                .map(|cow| Cow::Owned(cow.into_owned())),
-            subtitle: self.subtitle.clone()
-                .map(|cow| Cow::Owned(cow.into_owned())),
-            body: self.body.clone()
-                .map(|cow| Cow::Owned(cow.into_owned())),
-            title_loc_key: self.body.clone()
-                .map(|cow| Cow::Owned(cow.into_owned())),
-            title_loc_args: self.title_loc_args.as_ref()
-                .map(|vec| {
-                    vec
-                        .into_iter()
-                        .map(|cow| Cow::Owned(cow.clone().into_owned()))
-                        .collect()
-                }),
-            action_loc_key: self.action_loc_key.clone()
-                .map(|cow| Cow::Owned(cow.clone().into_owned())),
-            loc_key: self.loc_key.clone()
+            title: self.title.clone().map(|cow| Cow::Owned(cow.into_owned())),
+            subtitle: self.subtitle.clone().map(|cow| Cow::Owned(cow.into_owned())),
+            body: self.body.clone().map(|cow| Cow::Owned(cow.into_owned())),
+            title_loc_key: self.title_loc_key.clone().map(|cow| Cow::Owned(cow.into_owned())),
+            title_loc_args: self
+                .title_loc_args
+                .as_ref()
+                .map(|vec| vec.iter().map(|cow| Cow::Owned(cow.clone().into_owned())).collect()),
+            action_loc_key: self
+                .action_loc_key
+                .clone()
                 .map(|cow| Cow::Owned(cow.clone().into_owned())),
-            loc_args: self.loc_args.as_ref()
-                .map(|vec| {
-                    vec
-                        .into_iter()
-                        .map(|cow| Cow::Owned(cow.clone().into_owned()))
-                        .collect()
-                }),
-            launch_image: self.launch_image.clone()
+            loc_key: self.loc_key.clone().map(|cow| Cow::Owned(cow.clone().into_owned())),
+            loc_args: self
+                .loc_args
+                .as_ref()
+                .map(|vec| vec.iter().map(|cow| Cow::Owned(cow.clone().into_owned())).collect()),
+            launch_image: self
+                .launch_image
+                .clone()
                 .map(|cow| Cow::Owned(cow.clone().into_owned())),
         }
     }
 }
 
+/// Keeps the title and the body of the web alert. The action has no
+/// counterpart in a standard alert and is dropped.
+impl<'a> From<WebPushAlert<'a>> for DefaultAlert<'a> {
+    fn from(alert: WebPushAlert<'a>) -> Self {
+        DefaultAlert {
+            title: Some(alert.title),
+            subtitle: None,
+            body: Some(alert.body),
+            title_loc_key: None,
+            title_loc_args: None,
+            action_loc_key: None,
+            loc_key: None,
+            loc_args: None,
+            launch_image: None,
+        }
+    }
+}
+
+/// Converts an alert with a title and a body and nothing else, which a web
+/// alert holds without losing anything. The action of the web alert is left
+/// empty. Other alerts fail with [`Error::InvalidPayload`].
+impl<'a> TryFrom<DefaultAlert<'a>> for WebPushAlert<'a> {
+    type Error = Error;
+
+    fn try_from(alert: DefaultAlert<'a>) -> Result<Self, Error> {
+        match alert {
+            DefaultAlert {
+                title: Some(title),
+                body: Some(body),
+                subtitle: None,
+                title_loc_key: None,
+                title_loc_args: None,
+                action_loc_key: None,
+                loc_key: None,
+                loc_args: None,
+                launch_image: None,
+            } => Ok(WebPushAlert::new(title, body, "")),
+            _ => Err(Error::InvalidPayload(String::from(
+                "only an alert with just a title and a body converts to a web alert",
+            ))),
+        }
+    }
+}
+
 /// A builder to create an APNs payload.
 ///
+/// Calling a setter again replaces the value of the earlier call, the last
+/// call wins and none of the setters fail on a value that is already set. A
+/// setter can therefore set a default first and an override later in the
+/// chain, e.g. `set_sound` in shared code and `set_sound` again for a single
+/// notification. Setters that write the same key, such as `set_sound` and
+/// `set_silent_alert` or `set_critical_alert` and `set_interruption_level`,
+/// follow the same rule for that key.
+///
 /// # Example
 ///
 /// ```rust
-/// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+/// # use a2::request::notification::{CategoryId, DefaultNotificationBuilder, NotificationBuilder};
 /// # use a2::request::payload::PayloadLike;
 /// # fn main() {
 /// let mut builder = DefaultNotificationBuilder::new()
@@ -129,7 +308,7 @@ impl<'a> DefaultAlert<'a> {
 ///     .set_subtitle("From bob")
 ///     .set_body("What's up?")
 ///     .set_badge(420)
-///     .set_category("cat1")
+///     .set_category(CategoryId::new("cat1").unwrap())
 ///     .set_sound("prööt")
 ///     .set_critical(false, None)
 ///     .set_mutable_content()
@@ -150,9 +329,20 @@ pub struct DefaultNotificationBuilder<'a> {
     badge: Option<u32>,
     sound: DefaultSound<'a>,
     category: Option<Cow<'a, str>>,
-    mutable_content: u8,
-    content_available: Option<u8>,
+    mutable_content: Option<u8>,
+    content_available: bool,
     has_edited_alert: bool,
+    interruption_level: Option<InterruptionLevel>,
+    aps_extra: BTreeMap<Cow<'a, str>, Value>,
+    /// The action of a Safari alert the builder was created from, kept as
+    /// long as the alert has a title and a body
+    web_action: Option<Cow<'a, str>>,
+    /// The `aps` keys of the payload the builder was created from that the
+    /// builder has no setter for, such as the Live Activity keys
+    aps_rest: APS<'a>,
+    custom_data: CustomData<'a>,
+    droppable_data: Vec<Cow<'a, str>>,
+    custom_data_key_case: Option<KeyCase>,
 }
 
 impl<'a> DefaultNotificationBuilder<'a> {
@@ -193,16 +383,132 @@ impl<'a> DefaultNotificationBuilder<'a> {
                 volume: None,
             },
             category: None,
-            mutable_content: 0,
-            content_available: None,
+            mutable_content: Some(0),
+            content_available: false,
             has_edited_alert: false,
+            interruption_level: None,
+            aps_extra: BTreeMap::new(),
+            web_action: None,
+            aps_rest: APS::default(),
+            custom_data: CustomData::new(),
+            droppable_data: Vec::new(),
+            custom_data_key_case: None,
         }
     }
 
+    /// Creates a builder for the everyday alert with a title and a body.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::standard("a title", "a body")
+    ///     .set_badge(1)
+    ///     .set_default_sound()
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\",\"body\":\"a body\"},\"badge\":1,\"sound\":\"default\",\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn standard(title: impl Into<Cow<'a, str>>, body: impl Into<Cow<'a, str>>) -> DefaultNotificationBuilder<'a> {
+        DefaultNotificationBuilder::new().set_title(title).set_body(body)
+    }
+
+    /// Creates a builder for a time sensitive alert with a title and a body,
+    /// which breaks through Focus and scheduled summaries on iOS 15 and
+    /// later. The app needs the Time Sensitive Notifications entitlement, and
+    /// the notification should be one the user wants to see right away.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::time_sensitive("Your ride is here", "Look for a blue car")
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"Your ride is here\",\"body\":\"Look for a blue car\"},\"mutable-content\":0,\"interruption-level\":\"time-sensitive\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn time_sensitive(
+        title: impl Into<Cow<'a, str>>,
+        body: impl Into<Cow<'a, str>>,
+    ) -> DefaultNotificationBuilder<'a> {
+        DefaultNotificationBuilder::standard(title, body).set_interruption_level(InterruptionLevel::TimeSensitive)
+    }
+
+    /// Creates a builder with the content of an existing payload, for
+    /// example to change a single field of a deserialized payload. The `aps`
+    /// keys and the custom data are carried over as they are, the device
+    /// token and options are not part of the builder and have to be given to
+    /// [`build`](NotificationBuilder::build) again.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::{Payload, PayloadLike};
+    /// # fn main() {
+    /// let payload: Payload = serde_json::from_str(r#"{"aps":{"alert":"a body","badge":1},"id":42}"#).unwrap();
+    ///
+    /// let payload = DefaultNotificationBuilder::from_payload(&payload)
+    ///     .set_badge(2)
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":\"a body\",\"badge\":2},\"id\":42}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn from_payload(payload: &Payload<'a>) -> DefaultNotificationBuilder<'a> {
+        let mut aps = payload.aps.clone();
+        let mut builder = DefaultNotificationBuilder::new();
+
+        match aps.alert.take() {
+            Some(APSAlert::Default(alert)) => {
+                builder.alert = alert;
+                builder.has_edited_alert = true;
+            }
+            Some(APSAlert::WebPush(alert)) => {
+                builder.alert.title = Some(alert.title);
+                builder.alert.body = Some(alert.body);
+                builder.web_action = Some(alert.action);
+                builder.has_edited_alert = true;
+            }
+            Some(APSAlert::Body(body)) => builder.alert.body = Some(body),
+            None => (),
+        }
+
+        match aps.sound.take() {
+            Some(APSSound::Critical(sound)) => builder.sound = sound,
+            Some(APSSound::Sound(name)) => builder.sound.name = Some(name),
+            None => (),
+        }
+
+        builder.badge = aps.badge.take();
+        builder.category = aps.category.take();
+        builder.mutable_content = aps.mutable_content.take();
+        builder.content_available = aps.content_available.take() == Some(1);
+        builder.interruption_level = aps.interruption_level.take();
+        builder.aps_extra = std::mem::take(&mut aps.extra);
+        builder.aps_rest = aps;
+        builder.custom_data = payload.data.clone();
+        builder.droppable_data = payload.droppable_data().to_vec();
+        builder.custom_data_key_case = payload.custom_data_key_case();
+        builder
+    }
+
     /// Set the title of the notification.
     /// Apple Watch displays this string in the short look notification interface.
     /// Specify a string that’s quickly understood by the user.
     ///
+    /// A title alone makes a complete alert, the body is optional and left
+    /// out of the payload when not set.
+    ///
     /// ```rust
     /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
     /// # use a2::request::payload::PayloadLike;
@@ -224,7 +530,11 @@ impl<'a> DefaultNotificationBuilder<'a> {
     }
 
     /// Set critical alert value for this notification
-    /// Volume can only be set when the notification is marked as critcial
+    /// Volume can only be set when the notification is marked as critcial,
+    /// APNs ignores the volume of other sounds and `set_critical(false, _)`
+    /// drops it. A critical sound goes together with the `critical`
+    /// interruption level, which `set_critical(true, _)` sets and
+    /// `set_critical(false, _)` removes again.
     /// Note: You'll need the [critical alerts entitlement](https://developer.apple.com/contact/request/notifications-critical-alerts-entitlement/) to use `true`!
     ///
     /// ```rust
@@ -236,7 +546,7 @@ impl<'a> DefaultNotificationBuilder<'a> {
     /// let payload = builder.build("token", Default::default());
     ///
     /// assert_eq!(
-    ///     "{\"aps\":{\"sound\":{\"critical\":1},\"mutable-content\":0}}",
+    ///     "{\"aps\":{\"sound\":{\"critical\":1},\"mutable-content\":0,\"interruption-level\":\"critical\"}}",
     ///     &payload.to_json_string().unwrap()
     /// );
     /// # }
@@ -245,13 +555,93 @@ impl<'a> DefaultNotificationBuilder<'a> {
         if !critical {
             self.sound.volume = None;
             self.sound.critical = false;
+
+            if self.interruption_level == Some(InterruptionLevel::Critical) {
+                self.interruption_level = None;
+            }
         } else {
             self.sound.volume = volume;
             self.sound.critical = true;
+            self.interruption_level = Some(InterruptionLevel::Critical);
         }
         self
     }
 
+    /// Makes the notification a critical alert, setting the critical sound
+    /// together with the `critical` interruption level, which iOS 15 and
+    /// later expect for a critical sound to be played. The same as
+    /// [`set_critical(true, volume)`](Self::set_critical).
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .set_body("Intruder alert")
+    ///     .set_critical_alert(Some(0.8))
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":\"Intruder alert\",\"sound\":{\"critical\":1,\"volume\":0.8},\"mutable-content\":0,\"interruption-level\":\"critical\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_critical_alert(self, volume: Option<f64>) -> Self {
+        self.set_critical(true, volume)
+    }
+
+    /// Makes the notification a [critical alert](Self::set_critical_alert)
+    /// if `critical_available`, e.g. when the app holds the critical alerts
+    /// entitlement, and a regular alert with the default system sound
+    /// otherwise. The same call then builds the intended payload for both
+    /// cases.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .set_body("Intruder alert")
+    ///     .set_critical_alert_or_default_sound(false, Some(0.8))
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":\"Intruder alert\",\"sound\":\"default\",\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_critical_alert_or_default_sound(self, critical_available: bool, volume: Option<f64>) -> Self {
+        if critical_available {
+            self.set_critical_alert(volume)
+        } else {
+            self.set_critical(false, None).set_default_sound()
+        }
+    }
+
+    /// Sets how strongly the notification interrupts the user.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, InterruptionLevel, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .set_interruption_level(InterruptionLevel::Passive)
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":\"a body\",\"mutable-content\":0,\"interruption-level\":\"passive\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_interruption_level(mut self, interruption_level: InterruptionLevel) -> Self {
+        self.interruption_level = Some(interruption_level);
+        self
+    }
+
     /// Used to set the subtitle which should provide additional information that explains the purpose of the notification.
     ///
     /// ```rust
@@ -317,6 +707,8 @@ impl<'a> DefaultNotificationBuilder<'a> {
     }
 
     /// File name of the custom sound to play when receiving the notification.
+    /// A notification with a sound and no alert fields, like a subtle chime,
+    /// is sent without the `alert` key.
     ///
     /// ```rust
     /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
@@ -338,16 +730,60 @@ impl<'a> DefaultNotificationBuilder<'a> {
         self
     }
 
+    /// Play the default system sound when receiving the notification.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .set_title("a title")
+    ///     .set_default_sound();
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"sound\":\"default\",\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_default_sound(self) -> Self {
+        self.set_sound(APSSound::DEFAULT_SYSTEM)
+    }
+
+    /// Shows the alert without a sound, setting the sound to the explicit
+    /// [silence](APSSound::silent) instead of leaving it out.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = DefaultNotificationBuilder::new()
+    ///     .set_title("a title")
+    ///     .set_silent_alert();
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"a title\"},\"sound\":\"\",\"mutable-content\":0}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_silent_alert(self) -> Self {
+        self.set_sound(APSSound::SILENT)
+    }
+
     /// When a notification includes the category key, the system displays the
     /// actions for that category as buttons in the banner or alert interface.
+    /// Takes a [`CategoryId`], which is checked to be non-empty when created.
     ///
     /// ```rust
-    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::notification::{CategoryId, DefaultNotificationBuilder, NotificationBuilder};
     /// # use a2::request::payload::PayloadLike;
     /// # fn main() {
     /// let mut builder = DefaultNotificationBuilder::new()
     ///     .set_title("a title")
-    ///     .set_category("cat1");
+    ///     .set_category(CategoryId::new("cat1").unwrap());
     /// let payload = builder.build("token", Default::default());
     ///
     /// assert_eq!(
@@ -356,7 +792,7 @@ impl<'a> DefaultNotificationBuilder<'a> {
     /// );
     /// # }
     /// ```
-    pub fn set_category(mut self, category: impl Into<Cow<'a, str>>) -> Self {
+    pub fn set_category(mut self, category: CategoryId<'a>) -> Self {
         self.category = Some(category.into());
         self
     }
@@ -527,19 +963,24 @@ impl<'a> DefaultNotificationBuilder<'a> {
     /// # }
     /// ```
     pub fn set_mutable_content(mut self) -> Self {
-        self.mutable_content = 1;
+        self.mutable_content = Some(1);
         self
     }
 
     /// Used for adding custom data to push notifications
     ///
+    /// `true` sends `"content-available":1`, which wakes the app in the
+    /// background. `false` leaves the key out, as APNs treats any other
+    /// value the same as a missing key, so `"content-available":0` is never
+    /// sent.
+    ///
     /// ```rust
     /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
     /// # use a2::request::payload::PayloadLike;
     /// # fn main() {
     /// let mut builder = DefaultNotificationBuilder::new()
     ///     .set_title("a title")
-    ///     .set_content_available();
+    ///     .set_content_available(true);
     /// let payload = builder.build("token", Default::default());
     ///
     /// assert_eq!(
@@ -548,36 +989,121 @@ impl<'a> DefaultNotificationBuilder<'a> {
     /// );
     /// # }
     /// ```
-    pub fn set_content_available(mut self) -> Self {
-        self.content_available = Some(1);
+    pub fn set_content_available(mut self, content_available: bool) -> Self {
+        self.content_available = content_available;
+        self
+    }
+
+    /// Adds a key to the `aps` dictionary that has no setter here yet, e.g.
+    /// one Apple introduced after this version of the crate. Fails for the
+    /// keys in [`APS::KEYS`], which have to be set with their own setters.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .set_aps_extra("thread-id", "chat-42".into())
+    ///     .unwrap()
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":\"a body\",\"mutable-content\":0,\"thread-id\":\"chat-42\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_aps_extra(mut self, key: impl Into<Cow<'a, str>>, value: Value) -> Result<Self, Error> {
+        let key = key.into();
+
+        if APS::KEYS.contains(&key.as_ref()) {
+            return Err(Error::InvalidOptions(format!(
+                "The aps key {} has to be set with its own setter",
+                key
+            )));
+        }
+
+        self.aps_extra.insert(key, value);
+        Ok(self)
+    }
+
+    /// Converts the keys of the custom data added to the built payload to
+    /// the given case when it's serialized, see
+    /// [`Payload::set_custom_data_key_case`].
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::{KeyCase, PayloadLike};
+    /// # fn main() {
+    /// let mut payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .set_custom_data_key_case(KeyCase::CamelCase)
+    ///     .build("token", Default::default());
+    /// payload.add_custom_data("match_id", &42).unwrap();
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":\"a body\",\"mutable-content\":0},\"matchId\":42}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_custom_data_key_case(mut self, case: KeyCase) -> Self {
+        self.custom_data_key_case = Some(case);
         self
     }
 }
 
 impl<'a> NotificationBuilder<'a> for DefaultNotificationBuilder<'a> {
     fn build(self, device_token: impl Into<Cow<'a, str>>, options: NotificationOptions<'a>) -> Payload<'a> {
-        Payload {
-            aps: APS {
-                alert: match self.has_edited_alert {
-                    true => Some(APSAlert::Default(self.alert)),
-                    false => self.alert.body.map(APSAlert::Body),
-                },
-                badge: self.badge,
-                sound: if self.sound.critical {
-                    Some(APSSound::Critical(self.sound))
-                } else {
-                    self.sound.name.map(APSSound::Sound)
-                },
-                content_available: self.content_available,
-                category: self.category,
-                mutable_content: Some(self.mutable_content),
-                url_args: None,
+        let aps = APS {
+            // An empty alert dictionary is left out, APNs may reject it
+            alert: if self.alert.is_empty() {
+                None
+            } else if let Some(alert) = web_alert(&self.alert, self.web_action) {
+                Some(APSAlert::WebPush(alert))
+            } else if self.has_edited_alert {
+                Some(APSAlert::Default(self.alert))
+            } else {
+                self.alert.body.map(APSAlert::Body)
             },
-            device_token: device_token.into(),
-            options,
-            data: BTreeMap::new(),
-        }
+            badge: self.badge,
+            sound: if self.sound.critical {
+                Some(APSSound::Critical(self.sound))
+            } else {
+                self.sound.name.map(APSSound::Sound)
+            },
+            content_available: self.content_available.then(|| 1),
+            category: self.category,
+            mutable_content: self.mutable_content,
+            interruption_level: self.interruption_level,
+            extra: self.aps_extra,
+            ..self.aps_rest
+        };
+
+        let mut payload = Payload::new(aps, device_token, options);
+        payload.data = self.custom_data;
+        payload.set_droppable_data(self.droppable_data);
+        payload.set_custom_data_key_case(self.custom_data_key_case);
+        payload
+    }
+}
+
+/// The Safari alert of a builder created from one, as long as the alert is
+/// still just the title and the body a Safari alert consists of.
+fn web_alert<'a>(alert: &DefaultAlert<'a>, action: Option<Cow<'a, str>>) -> Option<WebPushAlert<'a>> {
+    let action = action?;
+    let only_title_and_body = DefaultAlert {
+        title: alert.title.clone(),
+        body: alert.body.clone(),
+        ..DefaultNotificationBuilder::new().alert
+    };
+
+    if *alert != only_title_and_body {
+        return None;
     }
+
+    Some(WebPushAlert::new(alert.title.clone()?, alert.body.clone()?, action))
 }
 
 impl<'a> Default for DefaultNotificationBuilder<'a> {
@@ -589,6 +1115,7 @@ impl<'a> Default for DefaultNotificationBuilder<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::request::payload::PayloadLike;
     use serde_json::value::to_value;
 
     #[test]
@@ -617,7 +1144,7 @@ mod tests {
             .set_title("the title")
             .set_body("the body")
             .set_badge(420)
-            .set_category("cat1")
+            .set_category(CategoryId::new("cat1").unwrap())
             .set_sound("prööt")
             .set_critical(true, Some(1.0))
             .set_mutable_content()
@@ -651,12 +1178,433 @@ mod tests {
                 },
                 "category": "cat1",
                 "mutable-content": 1,
+                "interruption-level": "critical",
+            }
+        });
+
+        assert_eq!(expected_payload, to_value(payload).unwrap());
+    }
+
+    #[test]
+    fn test_alert_with_localized_keys_and_body() {
+        let payload = DefaultNotificationBuilder::new()
+            .set_title("the title")
+            .set_subtitle("the subtitle")
+            .set_body("the fallback body")
+            .set_title_loc_key("TITLE")
+            .set_loc_key("BODY")
+            .set_loc_args(&["narf"])
+            .build("device-token", Default::default());
+
+        let expected_alert = json!({
+            "title": "the title",
+            "subtitle": "the subtitle",
+            "body": "the fallback body",
+            "title-loc-key": "TITLE",
+            "loc-key": "BODY",
+            "loc-args": ["narf"],
+        });
+
+        assert_eq!(expected_alert, to_value(&payload).unwrap()["aps"]["alert"]);
+
+        let Some(APSAlert::Default(ref alert)) = payload.aps.alert else {
+            panic!("unexpected alert {:?}", payload.aps.alert);
+        };
+        assert_eq!(expected_alert, to_value(alert.to_static()).unwrap());
+    }
+
+    #[test]
+    fn test_aps_extra_keys() {
+        let payload = DefaultNotificationBuilder::new()
+            .set_title("the title")
+            .set_aps_extra("future-key", json!({"nested": true}))
+            .unwrap()
+            .build("device-token", Default::default());
+
+        let expected_payload = json!({
+            "aps": {
+                "alert": {
+                    "title": "the title",
+                },
+                "mutable-content": 0,
+                "future-key": {"nested": true},
+            }
+        });
+
+        assert_eq!(expected_payload, to_value(&payload).unwrap());
+
+        let relayed: Payload = serde_json::from_value(expected_payload).unwrap();
+
+        assert_eq!(Some(&json!({"nested": true})), relayed.aps.extra.get("future-key"));
+        assert!(DefaultNotificationBuilder::new()
+            .set_aps_extra("badge", json!(1))
+            .is_err());
+    }
+
+    #[test]
+    fn test_critical_alert_sets_the_sound_and_interruption_level() {
+        let payload = DefaultNotificationBuilder::new()
+            .set_body("the body")
+            .set_critical_alert(Some(1.0))
+            .build("device-token", Default::default());
+
+        let expected_payload = json!({
+            "aps": {
+                "alert": "the body",
+                "sound": {
+                    "critical": 1,
+                    "volume": 1.0,
+                },
+                "interruption-level": "critical",
+                "mutable-content": 0,
+            }
+        });
+
+        assert_eq!(expected_payload, to_value(&payload).unwrap());
+        assert!(payload.validate().is_ok());
+
+        let relayed: Payload = serde_json::from_value(expected_payload).unwrap();
+        let rebuilt = DefaultNotificationBuilder::from_payload(&relayed).build("device-token", Default::default());
+
+        assert_eq!(Some(InterruptionLevel::Critical), rebuilt.aps.interruption_level);
+
+        let sound_only = DefaultNotificationBuilder::new()
+            .set_critical(true, Some(1.0))
+            .build("device-token", Default::default());
+
+        assert_eq!(Some(InterruptionLevel::Critical), sound_only.aps.interruption_level);
+        assert!(sound_only.validate().is_ok());
+
+        let interrupting = DefaultNotificationBuilder::new()
+            .set_critical(true, Some(1.0))
+            .set_interruption_level(InterruptionLevel::Active)
+            .build("device-token", Default::default());
+
+        assert!(matches!(interrupting.validate(), Err(Error::InvalidPayload(_))));
+
+        let uncritical = DefaultNotificationBuilder::new()
+            .set_critical(true, Some(1.0))
+            .set_critical(false, None)
+            .build("device-token", Default::default());
+
+        assert_eq!(None, uncritical.aps.interruption_level);
+    }
+
+    #[test]
+    fn test_time_sensitive_alert() {
+        let payload = DefaultNotificationBuilder::time_sensitive("the title", "the body")
+            .set_default_sound()
+            .build("device-token", Default::default());
+
+        let expected_payload = json!({
+            "aps": {
+                "alert": {
+                    "title": "the title",
+                    "body": "the body",
+                },
+                "sound": "default",
+                "interruption-level": "time-sensitive",
+                "mutable-content": 0,
+            }
+        });
+
+        assert_eq!(expected_payload, to_value(&payload).unwrap());
+        assert!(payload.validate().is_ok());
+    }
+
+    #[test]
+    fn test_alert_conversions_with_web_alerts() {
+        let alert = DefaultAlert::from(WebPushAlert::new("the title", "the body", "View"));
+
+        assert_eq!(Some("the title"), alert.title.as_deref());
+        assert_eq!(Some("the body"), alert.body.as_deref());
+        assert_eq!(
+            json!({ "title": "the title", "body": "the body" }),
+            to_value(&alert).unwrap()
+        );
+
+        let web_alert = WebPushAlert::try_from(alert).unwrap();
+
+        assert_eq!("the title", web_alert.title);
+        assert_eq!("the body", web_alert.body);
+        assert_eq!("", web_alert.action);
+
+        let builder = DefaultNotificationBuilder::standard("the title", "the body").set_subtitle("the subtitle");
+
+        assert!(matches!(
+            WebPushAlert::try_from(builder.alert),
+            Err(Error::InvalidPayload(_))
+        ));
+    }
+
+    #[test]
+    fn test_critical_alert_with_a_default_sound_fallback() {
+        let build = |critical_available| {
+            let payload = DefaultNotificationBuilder::new()
+                .set_body("the body")
+                .set_critical_alert_or_default_sound(critical_available, Some(0.5))
+                .build("device-token", Default::default());
+
+            to_value(payload).unwrap()
+        };
+
+        let critical = json!({
+            "aps": {
+                "alert": "the body",
+                "sound": {
+                    "critical": 1,
+                    "volume": 0.5,
+                },
+                "interruption-level": "critical",
+                "mutable-content": 0,
+            }
+        });
+        let degraded = json!({
+            "aps": {
+                "alert": "the body",
+                "sound": "default",
+                "mutable-content": 0,
+            }
+        });
+
+        assert_eq!(critical, build(true));
+        assert_eq!(degraded, build(false));
+    }
+
+    #[test]
+    fn test_setters_called_twice_keep_the_last_value() {
+        let payload = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .set_sound("first.aiff")
+            .set_sound("second.aiff")
+            .set_badge(1)
+            .set_badge(2)
+            .set_category(CategoryId::new("first").unwrap())
+            .set_category(CategoryId::new("second").unwrap())
+            .set_mutable_content()
+            .set_mutable_content()
+            .set_aps_extra("thread-id", json!("first"))
+            .unwrap()
+            .set_aps_extra("thread-id", json!("second"))
+            .unwrap()
+            .build("device-token", Default::default());
+
+        assert_eq!(
+            json!({
+                "alert": "a body",
+                "badge": 2,
+                "sound": "second.aiff",
+                "category": "second",
+                "mutable-content": 1,
+                "thread-id": "second",
+            }),
+            to_value(&payload).unwrap()["aps"]
+        );
+    }
+
+    #[test]
+    fn test_setters_of_the_same_key_keep_the_last_value() {
+        let payload = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .set_silent_alert()
+            .set_sound("ping.aiff")
+            .set_critical_alert(None)
+            .set_interruption_level(InterruptionLevel::TimeSensitive)
+            .build("device-token", Default::default());
+
+        assert_eq!(json!("ping.aiff"), to_value(&payload).unwrap()["aps"]["sound"]["name"]);
+        assert_eq!(Some(InterruptionLevel::TimeSensitive), payload.aps.interruption_level);
+    }
+
+    #[test]
+    fn test_category_id() {
+        assert!(matches!(CategoryId::new(""), Err(Error::InvalidOptions(_))));
+        assert!(matches!(CategoryId::try_from(""), Err(Error::InvalidOptions(_))));
+        assert!(matches!(
+            CategoryId::try_from(String::new()),
+            Err(Error::InvalidOptions(_))
+        ));
+        assert_eq!(
+            "MEETING_INVITE",
+            CategoryId::try_from("MEETING_INVITE").unwrap().as_str()
+        );
+
+        let invite = CategoryId::new("MEETING_INVITE").unwrap();
+        let payload = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .set_category(invite.clone())
+            .build("device-token", Default::default());
+
+        assert_eq!("MEETING_INVITE", invite.to_string());
+        assert_eq!(Some("MEETING_INVITE"), payload.aps.category.as_deref());
+    }
+
+    #[test]
+    fn test_content_available_false_omits_the_key() {
+        let payload = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .set_content_available(true)
+            .set_content_available(false)
+            .build("device-token", Default::default());
+
+        assert!(to_value(&payload).unwrap()["aps"].get("content-available").is_none());
+
+        let relayed: Payload = serde_json::from_str(r#"{"aps":{"alert":"a body","content-available":0}}"#).unwrap();
+        let payload = DefaultNotificationBuilder::from_payload(&relayed).build("device-token", Default::default());
+
+        assert!(to_value(&payload).unwrap()["aps"].get("content-available").is_none());
+    }
+
+    #[test]
+    fn test_silent_alert_is_not_an_unset_sound() {
+        let silent = DefaultNotificationBuilder::new()
+            .set_body("quiet")
+            .set_silent_alert()
+            .build("device-token", Default::default());
+        let unset = DefaultNotificationBuilder::new()
+            .set_body("quiet")
+            .build("device-token", Default::default());
+
+        assert!(silent.aps.sound.as_ref().unwrap().is_silent());
+        assert_eq!(json!(""), to_value(&silent).unwrap()["aps"]["sound"]);
+        assert!(to_value(&unset).unwrap()["aps"].get("sound").is_none());
+    }
+
+    #[test]
+    fn test_sound_volume_needs_a_critical_alert() {
+        let critical = DefaultNotificationBuilder::new()
+            .set_sound("siren.aiff")
+            .set_critical(true, Some(0.5))
+            .build("device-token", Default::default());
+
+        assert_eq!(
+            json!({"critical": 1, "name": "siren.aiff", "volume": 0.5}),
+            to_value(&critical.aps.sound).unwrap()
+        );
+
+        let regular = DefaultNotificationBuilder::new()
+            .set_sound("siren.aiff")
+            .set_critical(false, Some(0.5))
+            .build("device-token", Default::default());
+
+        assert_eq!(json!("siren.aiff"), to_value(&regular.aps.sound).unwrap());
+    }
+
+    #[test]
+    fn test_title_only_alert() {
+        let payload = DefaultNotificationBuilder::new()
+            .set_title("New match!")
+            .build("device-token", Default::default());
+
+        let aps = &to_value(&payload).unwrap()["aps"];
+
+        assert_eq!(json!({"title": "New match!"}), aps["alert"]);
+        assert!(aps["alert"].get("body").is_none());
+        assert!(payload.validate().is_ok());
+
+        let rebuilt = DefaultNotificationBuilder::from_payload(&payload).build("device-token", Default::default());
+
+        assert_eq!(payload, rebuilt);
+    }
+
+    #[test]
+    fn test_sound_only_notification_has_no_alert() {
+        let payload = DefaultNotificationBuilder::new()
+            .set_sound("chime.aiff")
+            .build("device-token", Default::default());
+
+        assert_eq!(
+            json!({"sound": "chime.aiff", "mutable-content": 0}),
+            to_value(&payload).unwrap()["aps"]
+        );
+        assert!(payload.validate().is_ok());
+    }
+
+    #[test]
+    fn test_empty_alert_is_omitted() {
+        let original: Payload = serde_json::from_str(r#"{"aps":{"alert":{},"badge":1}}"#).unwrap();
+        let payload = DefaultNotificationBuilder::from_payload(&original).build("device-token", Default::default());
+
+        let expected_payload = json!({
+            "aps": {
+                "badge": 1
             }
         });
 
         assert_eq!(expected_payload, to_value(payload).unwrap());
     }
 
+    #[test]
+    fn test_edit_deserialized_payload() {
+        let json = r#"{
+            "aps": {
+                "alert": {"title": "the title", "body": "the body"},
+                "badge": 1,
+                "sound": "ping",
+                "mutable-content": 1
+            },
+            "custom": {"foo": "bar"}
+        }"#;
+
+        let original: Payload = serde_json::from_str(json).unwrap();
+        let payload = DefaultNotificationBuilder::from_payload(&original)
+            .set_badge(2)
+            .build("device-token", Default::default());
+
+        let expected_payload = json!({
+            "aps": {
+                "alert": {
+                    "title": "the title",
+                    "body": "the body",
+                },
+                "badge": 2,
+                "sound": "ping",
+                "mutable-content": 1
+            },
+            "custom": {"foo": "bar"}
+        });
+
+        assert_eq!(expected_payload, to_value(payload).unwrap());
+    }
+
+    #[test]
+    fn test_from_payload_round_trips_the_whole_payload() {
+        let json = json!({
+            "aps": {
+                "alert": {"title": "the title", "body": "the body"},
+                "badge": 1,
+                "url-args": ["a", "b"],
+                "timestamp": 1_700_000_000,
+                "event": "update",
+                "content-state": {"score": 3},
+                "stale-date": 1_700_003_600,
+                "x-unmodeled": true
+            },
+            "custom": {"foo": "bar"},
+            "debug": "trace"
+        });
+
+        let mut original: Payload = serde_json::from_value(json.clone()).unwrap();
+        original.add_droppable_custom_data("debug", &"trace").unwrap();
+
+        let rebuilt = DefaultNotificationBuilder::from_payload(&original).build("", Default::default());
+
+        assert_eq!(json, to_value(&rebuilt).unwrap());
+        assert_eq!(original, rebuilt);
+        assert_eq!(original.droppable_data(), rebuilt.droppable_data());
+
+        let web: Payload =
+            serde_json::from_str(r#"{"aps":{"alert":{"title":"a title","body":"a body","action":"View"}}}"#).unwrap();
+
+        let rebuilt = DefaultNotificationBuilder::from_payload(&web).build("", Default::default());
+        assert_eq!(web, rebuilt);
+
+        let edited = DefaultNotificationBuilder::from_payload(&web)
+            .set_subtitle("a subtitle")
+            .build("", Default::default());
+        assert!(matches!(edited.aps.alert, Some(APSAlert::Default(_))));
+    }
+
     #[test]
     fn test_notification_with_custom_data_1() {
         #[derive(Serialize, Debug)]
@@ -756,7 +1704,7 @@ mod tests {
     #[test]
     fn test_silent_notification_with_no_content() {
         let payload = DefaultNotificationBuilder::new()
-            .set_content_available()
+            .set_content_available(true)
             .build("device-token", Default::default());
 
         let expected_payload = json!({
@@ -792,7 +1740,7 @@ mod tests {
         };
 
         let mut payload = DefaultNotificationBuilder::new()
-            .set_content_available()
+            .set_content_available(true)
             .build("device-token", Default::default());
 
         payload.add_custom_data("custom", &test_data).unwrap();
@@ -822,7 +1770,7 @@ mod tests {
         test_data.insert("key_str2", "bar");
 
         let mut payload = DefaultNotificationBuilder::new()
-            .set_content_available()
+            .set_content_available(true)
             .build("device-token", Default::default());
 
         payload.add_custom_data("custom", &test_data).unwrap();
@@ -0,0 +1,328 @@
+use crate::error::Error;
+use crate::request::notification::{NotificationBuilder, NotificationOptions, Priority, PushType};
+use crate::request::payload::{Payload, APS};
+use erased_serde::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The key of the schema version in a versioned content state.
+const CONTENT_STATE_VERSION: &str = "version";
+
+/// What a Live Activity notification does to the activity.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub enum LiveActivityEvent {
+    /// Starts a new Live Activity
+    Start,
+    /// Updates the content of a running Live Activity
+    Update,
+    /// Ends a running Live Activity
+    End,
+}
+
+impl LiveActivityEvent {
+    /// The `apns-priority` a Live Activity payload is sent with unless the
+    /// options or the
+    /// [push type defaults](crate::client::ClientConfig::with_push_type_defaults)
+    /// of the client for [`PushType::LiveActivity`] set one: high for
+    /// starting and ending an activity, which should show right away, and
+    /// normal for updates.
+    ///
+    /// The system gives every app a budget of high priority Live Activity
+    /// updates per hour, depending on the conditions of the device, and
+    /// throttles the updates above it, dropping the ones in between. Updates
+    /// with normal priority don't count against the budget and are shown
+    /// opportunistically, which suits frequent updates such as a score or
+    /// an arrival time. Send an update that has to show immediately with
+    /// [`Priority::High`] in the options.
+    pub fn default_priority(self) -> Priority {
+        match self {
+            LiveActivityEvent::Start | LiveActivityEvent::End => Priority::High,
+            LiveActivityEvent::Update => Priority::Normal,
+        }
+    }
+}
+
+/// A builder to create a notification payload for updating a Live Activity.
+///
+/// # Example
+///
+/// ```rust
+/// # use a2::request::notification::{LiveActivityEvent, LiveActivityNotificationBuilder, NotificationBuilder};
+/// # use a2::request::payload::PayloadLike;
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// # fn main() {
+/// let payload = LiveActivityNotificationBuilder::new(LiveActivityEvent::Update)
+///     .set_content_state(&serde_json::json!({"score": "2-1"}))
+///     .unwrap()
+///     .set_timestamp(UNIX_EPOCH + Duration::from_secs(1700000000))
+///     .build("token", Default::default());
+///
+/// assert_eq!(
+///     "{\"aps\":{\"timestamp\":1700000000,\"event\":\"update\",\"content-state\":{\"score\":\"2-1\"}}}",
+///     &payload.to_json_string().unwrap()
+/// );
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LiveActivityNotificationBuilder {
+    event: LiveActivityEvent,
+    content_state: Option<Value>,
+    timestamp: Option<SystemTime>,
+    dismissal_date: Option<SystemTime>,
+    stale_date: Option<SystemTime>,
+}
+
+impl LiveActivityNotificationBuilder {
+    /// Creates a new builder for the given event.
+    pub fn new(event: LiveActivityEvent) -> Self {
+        Self {
+            event,
+            content_state: None,
+            timestamp: None,
+            dismissal_date: None,
+            stale_date: None,
+        }
+    }
+
+    /// The new state of the activity, used by the app to render the updated
+    /// Live Activity. Fails if the state cannot be serialized.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{LiveActivityEvent, LiveActivityNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// # fn main() {
+    /// let payload = LiveActivityNotificationBuilder::new(LiveActivityEvent::Update)
+    ///     .set_content_state(&serde_json::json!({"courier": "Alice", "minutes": 5}))
+    ///     .unwrap()
+    ///     .set_timestamp(UNIX_EPOCH)
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"timestamp\":0,\"event\":\"update\",\"content-state\":{\"courier\":\"Alice\",\"minutes\":5}}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_content_state(mut self, content_state: &dyn Serialize) -> Result<Self, Error> {
+        self.content_state = Some(serde_json::to_value(content_state)?);
+        Ok(self)
+    }
+
+    /// The new state of the activity like
+    /// [`set_content_state`](Self::set_content_state), with a `version` key
+    /// added to it. Declaring a `version` property in the `ContentState` of
+    /// the activity lets the app tell which schema an update was sent with
+    /// and migrate older states as the activity evolves. Fails if the state
+    /// does not serialize to a JSON object or already has a `version` key.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{LiveActivityEvent, LiveActivityNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # use std::time::UNIX_EPOCH;
+    /// # fn main() {
+    /// let payload = LiveActivityNotificationBuilder::new(LiveActivityEvent::Update)
+    ///     .set_versioned_content_state(2, &serde_json::json!({"score": "2-1"}))
+    ///     .unwrap()
+    ///     .set_timestamp(UNIX_EPOCH)
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"timestamp\":0,\"event\":\"update\",\"content-state\":{\"score\":\"2-1\",\"version\":2}}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_versioned_content_state(mut self, version: u32, content_state: &dyn Serialize) -> Result<Self, Error> {
+        let mut content_state = match serde_json::to_value(content_state)? {
+            Value::Object(content_state) => content_state,
+            _ => {
+                return Err(Error::InvalidPayload(String::from(
+                    "a versioned content state has to be a JSON object",
+                )))
+            }
+        };
+
+        if content_state.contains_key(CONTENT_STATE_VERSION) {
+            return Err(Error::InvalidPayload(format!(
+                "the content state already has a {} key",
+                CONTENT_STATE_VERSION
+            )));
+        }
+
+        content_state.insert(String::from(CONTENT_STATE_VERSION), Value::from(version));
+        self.content_state = Some(Value::Object(content_state));
+
+        Ok(self)
+    }
+
+    /// When the update was generated. The system ignores updates with an
+    /// older timestamp than the one it last showed, so updates sent out of
+    /// order are dropped. Defaults to the time the payload is built.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{LiveActivityEvent, LiveActivityNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// # fn main() {
+    /// let payload = LiveActivityNotificationBuilder::new(LiveActivityEvent::Update)
+    ///     .set_timestamp(UNIX_EPOCH + Duration::from_secs(1700000000))
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"timestamp\":1700000000,\"event\":\"update\"}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_timestamp(mut self, timestamp: SystemTime) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// When an ended Live Activity is removed from the lock screen.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{LiveActivityEvent, LiveActivityNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// # fn main() {
+    /// let payload = LiveActivityNotificationBuilder::new(LiveActivityEvent::End)
+    ///     .set_timestamp(UNIX_EPOCH)
+    ///     .set_dismissal_date(UNIX_EPOCH + Duration::from_secs(3600))
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"timestamp\":0,\"event\":\"end\",\"dismissal-date\":3600}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_dismissal_date(mut self, dismissal_date: SystemTime) -> Self {
+        self.dismissal_date = Some(dismissal_date);
+        self
+    }
+
+    /// When the shown state of the Live Activity becomes outdated.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{LiveActivityEvent, LiveActivityNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// # fn main() {
+    /// let payload = LiveActivityNotificationBuilder::new(LiveActivityEvent::Update)
+    ///     .set_timestamp(UNIX_EPOCH)
+    ///     .set_stale_date(UNIX_EPOCH + Duration::from_secs(600))
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"timestamp\":0,\"event\":\"update\",\"stale-date\":600}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_stale_date(mut self, stale_date: SystemTime) -> Self {
+        self.stale_date = Some(stale_date);
+        self
+    }
+}
+
+/// Seconds since the Unix epoch, zero for times before it.
+fn epoch_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+impl<'a> NotificationBuilder<'a> for LiveActivityNotificationBuilder {
+    /// Builds the payload, using the `liveactivity` push type unless the
+    /// options set another. The payload is sent with the
+    /// [default priority](LiveActivityEvent::default_priority) of the event
+    /// when neither the options nor the client set a priority.
+    fn build(self, device_token: impl Into<Cow<'a, str>>, mut options: NotificationOptions<'a>) -> Payload<'a> {
+        options.apns_push_type.get_or_insert(PushType::LiveActivity);
+
+        let aps = APS {
+            timestamp: Some(epoch_secs(self.timestamp.unwrap_or_else(SystemTime::now))),
+            event: Some(self.event),
+            content_state: self.content_state,
+            dismissal_date: self.dismissal_date.map(epoch_secs),
+            stale_date: self.stale_date.map(epoch_secs),
+            ..Default::default()
+        };
+
+        Payload::new(aps, device_token, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::payload::PayloadLike;
+    use std::time::Duration;
+
+    #[test]
+    fn test_live_activity_default_timestamp_is_now() {
+        let before = epoch_secs(SystemTime::now());
+        let payload =
+            LiveActivityNotificationBuilder::new(LiveActivityEvent::Update).build("token", Default::default());
+        let after = epoch_secs(SystemTime::now());
+
+        let timestamp = payload.aps.timestamp.unwrap();
+
+        assert!(before <= timestamp && timestamp <= after);
+        assert_eq!(Some(PushType::LiveActivity), payload.options.apns_push_type);
+    }
+
+    #[test]
+    fn test_live_activity_timestamp_override() {
+        let payload = LiveActivityNotificationBuilder::new(LiveActivityEvent::Update)
+            .set_timestamp(UNIX_EPOCH + Duration::from_secs(1234))
+            .build("token", Default::default());
+
+        assert_eq!(Some(1234), payload.aps.timestamp);
+    }
+
+    #[test]
+    fn test_live_activity_push_type_override() {
+        let options = NotificationOptions {
+            apns_push_type: Some(PushType::Alert),
+            ..Default::default()
+        };
+        let payload = LiveActivityNotificationBuilder::new(LiveActivityEvent::End).build("token", options);
+
+        assert_eq!(Some(PushType::Alert), payload.options.apns_push_type);
+    }
+
+    #[test]
+    fn test_live_activity_priority_by_event() {
+        let update = LiveActivityNotificationBuilder::new(LiveActivityEvent::Update).build("token", Default::default());
+        let end = LiveActivityNotificationBuilder::new(LiveActivityEvent::End).build("token", Default::default());
+        let start = LiveActivityNotificationBuilder::new(LiveActivityEvent::Start).build("token", Default::default());
+
+        assert_eq!(Some(Priority::Normal), update.default_priority());
+        assert_eq!(Some(Priority::High), end.default_priority());
+        assert_eq!(Some(Priority::High), start.default_priority());
+        assert_eq!(None, update.options.apns_priority);
+    }
+
+    #[test]
+    fn test_live_activity_versioned_content_state() {
+        let payload = LiveActivityNotificationBuilder::new(LiveActivityEvent::Update)
+            .set_versioned_content_state(3, &json!({"eta": 5, "courier": "Alice"}))
+            .unwrap()
+            .build("token", Default::default());
+
+        assert_eq!(
+            Some(json!({"eta": 5, "courier": "Alice", "version": 3})),
+            payload.aps.content_state
+        );
+
+        assert!(LiveActivityNotificationBuilder::new(LiveActivityEvent::Update)
+            .set_versioned_content_state(1, &"not an object")
+            .is_err());
+        assert!(LiveActivityNotificationBuilder::new(LiveActivityEvent::Update)
+            .set_versioned_content_state(1, &json!({"version": 0}))
+            .is_err());
+    }
+}
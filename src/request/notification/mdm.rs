@@ -0,0 +1,70 @@
+use crate::request::notification::{NotificationBuilder, NotificationOptions, PushType};
+use crate::request::payload::{Payload, APS};
+use std::borrow::Cow;
+
+/// A builder to create the payload telling a device enrolled in mobile
+/// device management to contact its MDM server. The payload only has the
+/// `mdm` key with the push magic from the device check-in, without an `aps`
+/// dictionary.
+///
+/// # Example
+///
+/// ```rust
+/// # use a2::request::notification::{MdmNotificationBuilder, NotificationBuilder, PushType};
+/// # use a2::request::payload::PayloadLike;
+/// # fn main() {
+/// let payload = MdmNotificationBuilder::new("push-magic").build("token", Default::default());
+///
+/// assert_eq!("{\"mdm\":\"push-magic\"}", &payload.to_json_string().unwrap());
+/// assert_eq!(Some(PushType::Mdm), payload.options.apns_push_type);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MdmNotificationBuilder<'a> {
+    push_magic: Cow<'a, str>,
+}
+
+impl<'a> MdmNotificationBuilder<'a> {
+    /// Creates a new builder with the `PushMagic` string the device sent in
+    /// its `TokenUpdate` check-in.
+    pub fn new(push_magic: impl Into<Cow<'a, str>>) -> Self {
+        Self {
+            push_magic: push_magic.into(),
+        }
+    }
+}
+
+impl<'a> NotificationBuilder<'a> for MdmNotificationBuilder<'a> {
+    /// Builds the payload, using the `mdm` push type unless the options set
+    /// another one.
+    fn build(self, device_token: impl Into<Cow<'a, str>>, mut options: NotificationOptions<'a>) -> Payload<'a> {
+        options.apns_push_type.get_or_insert(PushType::Mdm);
+
+        let mut payload = Payload::new(APS::default(), device_token, options);
+        payload
+            .data
+            .insert(Cow::Borrowed("mdm"), self.push_magic.into_owned().into());
+
+        payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::payload::PayloadLike;
+
+    #[test]
+    fn test_mdm_payload_has_no_aps() {
+        let payload = MdmNotificationBuilder::new("token").build("device-token", Default::default());
+
+        assert_eq!(r#"{"mdm":"token"}"#, payload.to_json_string().unwrap());
+        assert_eq!(Some(PushType::Mdm), payload.options.apns_push_type);
+        assert_eq!(Ok(()), Payload::validate_json(&payload.to_json_string().unwrap()));
+
+        let relayed: Payload = serde_json::from_str(r#"{"mdm":"token"}"#).unwrap();
+
+        assert!(relayed.aps.is_empty());
+        assert_eq!(r#"{"mdm":"token"}"#, relayed.to_json_string().unwrap());
+    }
+}
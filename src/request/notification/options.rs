@@ -1,8 +1,12 @@
-use std::borrow::Cow;
+use crate::clock::{Clock, SystemClock};
 use crate::error::Error;
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CollapseId<'a> {
     pub value: Cow<'a, str>,
 }
@@ -19,9 +23,36 @@ impl<'a> CollapseId<'a> {
             Ok(CollapseId { value })
         }
     }
+
+    /// The collapse-id as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+}
+
+impl<'a> fmt::Display for CollapseId<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)
+    }
 }
 
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+impl<'a> TryFrom<&'a str> for CollapseId<'a> {
+    type Error = Error;
+
+    fn try_from(value: &'a str) -> Result<Self, Self::Error> {
+        CollapseId::new(value)
+    }
+}
+
+impl TryFrom<String> for CollapseId<'static> {
+    type Error = Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        CollapseId::new(value)
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 /// The apns-push-type header field has the following valid values.
 /// The descriptions below describe when and how to use these values.
 /// Send an apns-push-type header with each push. Recent and upcoming features
@@ -37,7 +68,12 @@ pub enum PushType {
     /// The push type for notifications that deliver content in the background, and
     /// don’t trigger any user interactions.
     Background,
-    /// The push type for notifications that request a user’s location.
+    /// The push type for notifications that request a user’s location. The
+    /// `apns-topic` must be the bundle ID with `.location-query` appended,
+    /// and only token-based authentication is supported. Use
+    /// [`Priority::High`] when the location query needs an immediate answer
+    /// from the Location Push Service Extension, otherwise
+    /// [`Priority::Normal`].
     Location,
     /// The push type for notifications that provide information about an incoming
     /// Voice-over-IP (VoIP) call.
@@ -50,8 +86,14 @@ pub enum PushType {
     ///  The push type to signal changes to a live activity session.
     LiveActivity,
     /// The push type for notifications that provide information about updates to
-    /// your application’s push to talk services.
+    /// your application’s push to talk services. The `apns-topic` must be the
+    /// bundle ID with `.voip-ptt` appended, and the notification should be
+    /// sent with [`Priority::High`] and an expiration of zero, see
+    /// [`PushToTalkNotificationBuilder`](super::PushToTalkNotificationBuilder).
     PushToTalk,
+    /// The push type for notifications that contain update information for a
+    /// watchOS app’s complications.
+    Complication,
 }
 
 impl fmt::Display for PushType {
@@ -65,12 +107,80 @@ impl fmt::Display for PushType {
             PushType::Mdm => "mdm",
             PushType::LiveActivity => "liveactivity",
             PushType::PushToTalk => "pushtotalk",
+            PushType::Complication => "complication",
         })
     }
 }
 
+impl PushType {
+    /// All push types APNs supports.
+    pub const ALL: [PushType; 9] = [
+        PushType::Alert,
+        PushType::Background,
+        PushType::Location,
+        PushType::Voip,
+        PushType::FileProvider,
+        PushType::Mdm,
+        PushType::LiveActivity,
+        PushType::PushToTalk,
+        PushType::Complication,
+    ];
+
+    /// The suffix APNs requires after the bundle ID in the `apns-topic` of
+    /// this push type. `None` for the push types sent to the plain bundle ID,
+    /// and for `Mdm`, which uses the topic of the MDM push certificate.
+    pub fn topic_suffix(&self) -> Option<&'static str> {
+        match self {
+            PushType::Alert | PushType::Background | PushType::Mdm => None,
+            PushType::Location => Some(".location-query"),
+            PushType::Voip => Some(".voip"),
+            PushType::FileProvider => Some(".pushkit.fileprovider"),
+            PushType::LiveActivity => Some(".push-type.liveactivity"),
+            PushType::PushToTalk => Some(".voip-ptt"),
+            PushType::Complication => Some(".complication"),
+        }
+    }
+
+    /// The `apns-topic` of this push type for the app with the given bundle
+    /// ID.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::PushType;
+    /// # fn main() {
+    /// assert_eq!("com.example.app.voip-ptt", PushType::PushToTalk.topic("com.example.app"));
+    /// assert_eq!("com.example.app", PushType::Alert.topic("com.example.app"));
+    /// # }
+    /// ```
+    pub fn topic(&self, bundle_id: &str) -> String {
+        format!("{}{}", bundle_id, self.topic_suffix().unwrap_or_default())
+    }
+}
+
+impl FromStr for PushType {
+    type Err = Error;
+
+    /// Parses the value of an `apns-push-type` header.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "alert" => Ok(PushType::Alert),
+            "background" => Ok(PushType::Background),
+            "location" => Ok(PushType::Location),
+            "voip" => Ok(PushType::Voip),
+            "fileprovider" => Ok(PushType::FileProvider),
+            "mdm" => Ok(PushType::Mdm),
+            "liveactivity" => Ok(PushType::LiveActivity),
+            "pushtotalk" => Ok(PushType::PushToTalk),
+            "complication" => Ok(PushType::Complication),
+            _ => Err(Error::InvalidOptions(format!("Unknown apns-push-type {}", s))),
+        }
+    }
+}
+
 /// Headers to specify options to the notification.
-#[derive(Debug, Default, Clone)]
+///
+/// The string values are `Cow`s, so options with a topic or collapse id known
+/// at startup can borrow a `&'static str` without allocating for every send.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
 pub struct NotificationOptions<'a> {
     /// A canonical UUID that identifies the notification. If there is an error
     /// sending the notification, APNs uses this value to identify the
@@ -91,6 +201,15 @@ pub struct NotificationOptions<'a> {
     /// to deliver the notification the first time. If the value is 0, APNs
     /// treats the notification as if it expires immediately and does not store
     /// the notification or attempt to redeliver it.
+    ///
+    /// `Some(0)` sends `apns-expiration: 0`, while `None` omits the header
+    /// and leaves the storage policy to APNs.
+    ///
+    /// APNs stores only the latest undelivered notification of a device, or
+    /// of a device and `apns_collapse_id`, and only for a limited period, so
+    /// an expiration weeks out doesn't keep a notification any longer; see
+    /// [`ClientConfig::with_expiration_clamp`](crate::client::ClientConfig::with_expiration_clamp)
+    /// to cap it at 28 days.
     pub apns_expiration: Option<u64>,
 
     /// The priority of the notification. If `None`, the APNs server sets the priority to High.
@@ -118,8 +237,131 @@ pub struct NotificationOptions<'a> {
     pub apns_collapse_id: Option<CollapseId<'a>>,
 }
 
+impl<'a> NotificationOptions<'a> {
+    /// Leaves out the `apns-expiration` header, so APNs stores the
+    /// notification and keeps trying to deliver it for as long as its
+    /// storage policy allows. The same as an unset `apns_expiration`, for
+    /// stating the choice in code. A default expiration set for the push
+    /// type with
+    /// [`ClientConfig::with_push_type_defaults`](crate::client::ClientConfig::with_push_type_defaults)
+    /// still applies.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::NotificationOptions;
+    /// # fn main() {
+    /// let options = NotificationOptions::default().expire_immediately().store_indefinitely();
+    ///
+    /// assert_eq!(None, options.apns_expiration);
+    /// # }
+    /// ```
+    pub fn store_indefinitely(mut self) -> Self {
+        self.apns_expiration = None;
+        self
+    }
+
+    /// Sets the `apns_expiration` to 0, so APNs tries to deliver the
+    /// notification once and discards it if the device is offline.
+    pub fn expire_immediately(mut self) -> Self {
+        self.apns_expiration = Some(0);
+        self
+    }
+
+    /// Sets the `apns_expiration` to `ttl` from now.
+    pub fn with_expiration_in(self, ttl: Duration) -> Self {
+        self.with_expiration_in_from(ttl, &SystemClock)
+    }
+
+    /// Sets the `apns_expiration` to `ttl` from the current time of the
+    /// clock.
+    ///
+    /// ```rust
+    /// # use a2::clock::ManualClock;
+    /// # use a2::request::notification::NotificationOptions;
+    /// # use std::time::{Duration, UNIX_EPOCH};
+    /// # fn main() {
+    /// let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(1700000000));
+    /// let options = NotificationOptions::default().with_expiration_in_from(Duration::from_secs(3600), &clock);
+    ///
+    /// assert_eq!(Some(1700003600), options.apns_expiration);
+    /// # }
+    /// ```
+    pub fn with_expiration_in_from(mut self, ttl: Duration, clock: &dyn Clock) -> Self {
+        self.apns_expiration = Some(clock.epoch_secs().saturating_add(ttl.as_secs()));
+        self
+    }
+
+    /// Parses the APNs request headers, e.g. the ones an API gateway
+    /// received from its own clients, into the options: `apns-id`,
+    /// `apns-push-type`, `apns-expiration`, `apns-priority`, `apns-topic`
+    /// and `apns-collapse-id`. Other headers are ignored. Fails with
+    /// [`InvalidOptions`](Error::InvalidOptions) on a malformed value.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{NotificationOptions, Priority};
+    /// # use http::HeaderMap;
+    /// # fn main() {
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert("apns-topic", "com.example.app".parse().unwrap());
+    /// headers.insert("apns-priority", "5".parse().unwrap());
+    ///
+    /// let options = NotificationOptions::from_headers(&headers).unwrap();
+    ///
+    /// assert_eq!(Some("com.example.app"), options.apns_topic.as_deref());
+    /// assert_eq!(Some(Priority::Normal), options.apns_priority);
+    /// # }
+    /// ```
+    #[cfg(feature = "client")]
+    pub fn from_headers(headers: &'a http::HeaderMap) -> Result<Self, Error> {
+        let header = |name: &str| {
+            headers
+                .get(name)
+                .map(|value| {
+                    value
+                        .to_str()
+                        .map_err(|_| Error::InvalidOptions(format!("The {} header is not valid text", name)))
+                })
+                .transpose()
+        };
+
+        let apns_id = match header("apns-id")? {
+            Some(id) if !is_canonical_uuid(id) => {
+                return Err(Error::InvalidOptions(format!("The apns-id {} is not a UUID", id)));
+            }
+            id => id.map(Cow::Borrowed),
+        };
+        let apns_expiration = header("apns-expiration")?
+            .map(|expiration| {
+                expiration
+                    .parse()
+                    .map_err(|_| Error::InvalidOptions(format!("Invalid apns-expiration {}", expiration)))
+            })
+            .transpose()?;
+
+        Ok(NotificationOptions {
+            apns_id,
+            apns_push_type: header("apns-push-type")?.map(str::parse).transpose()?,
+            apns_expiration,
+            apns_priority: header("apns-priority")?.map(str::parse).transpose()?,
+            apns_topic: header("apns-topic")?.map(Cow::Borrowed),
+            apns_collapse_id: header("apns-collapse-id")?.map(CollapseId::new).transpose()?,
+        })
+    }
+}
+
+/// True for the 8-4-4-4-12 hexadecimal digits of a UUID.
+#[cfg(feature = "client")]
+fn is_canonical_uuid(id: &str) -> bool {
+    let groups: Vec<&str> = id.split('-').collect();
+
+    groups.len() == 5
+        && groups
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
 /// The importance how fast to bring the notification for the user..
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Priority {
     /// Send the push message immediately. Notifications with this priority must
     /// trigger an alert, sound, or badge on the target device. Cannot be used
@@ -144,10 +386,25 @@ impl fmt::Display for Priority {
     }
 }
 
+impl FromStr for Priority {
+    type Err = Error;
+
+    /// Parses the value of an `apns-priority` header.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "10" => Ok(Priority::High),
+            "5" => Ok(Priority::Normal),
+            _ => Err(Error::InvalidOptions(format!("Invalid apns-priority {}", s))),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::ManualClock;
     use std::str;
+    use std::time::UNIX_EPOCH;
 
     #[test]
     fn test_collapse_id_under_64_chars() {
@@ -163,4 +420,141 @@ mod tests {
         let collapse_id = CollapseId::new(str::from_utf8(&long_string).unwrap());
         assert!(collapse_id.is_err());
     }
+
+    #[test]
+    fn test_collapse_id_conversions() {
+        let from_str = CollapseId::try_from("foo").unwrap();
+        let from_string = CollapseId::try_from(String::from("foo")).unwrap();
+
+        assert_eq!("foo", from_str.as_str());
+        assert_eq!("foo", from_str.to_string());
+        assert_eq!(from_str, from_string);
+        assert_ne!(from_str, CollapseId::try_from("bar").unwrap());
+        assert!(CollapseId::try_from("x".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_location_push_type() {
+        assert_eq!("location", PushType::Location.to_string());
+        assert_eq!(PushType::Location, "location".parse().unwrap());
+        assert!("locations".parse::<PushType>().is_err());
+    }
+
+    #[test]
+    fn test_all_push_types() {
+        let documented = [
+            ("alert", None),
+            ("background", None),
+            ("location", Some(".location-query")),
+            ("voip", Some(".voip")),
+            ("fileprovider", Some(".pushkit.fileprovider")),
+            ("mdm", None),
+            ("liveactivity", Some(".push-type.liveactivity")),
+            ("pushtotalk", Some(".voip-ptt")),
+            ("complication", Some(".complication")),
+        ];
+
+        assert_eq!(documented.len(), PushType::ALL.len());
+
+        for (push_type, (header, suffix)) in PushType::ALL.iter().zip(documented) {
+            assert_eq!(header, push_type.to_string());
+            assert_eq!(*push_type, header.parse().unwrap());
+            assert_eq!(suffix, push_type.topic_suffix());
+        }
+    }
+
+    #[test]
+    fn test_expiration_helpers() {
+        let options = NotificationOptions::default().expire_immediately();
+        assert_eq!(Some(0), options.apns_expiration);
+
+        let options = options.store_indefinitely();
+        assert_eq!(None, options.apns_expiration);
+    }
+
+    #[test]
+    fn test_expiration_in_from_a_fixed_clock() {
+        let fixed_now = 1700000000;
+        let clock = ManualClock::new(UNIX_EPOCH + Duration::from_secs(fixed_now));
+
+        let options = NotificationOptions::default().with_expiration_in_from(Duration::from_secs(60), &clock);
+
+        assert_eq!(Some(fixed_now + 60), options.apns_expiration);
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_options_from_headers() {
+        let mut headers = http::HeaderMap::new();
+        headers.insert("apns-id", "123e4567-e89b-12d3-a456-4266554400a0".parse().unwrap());
+        headers.insert("apns-push-type", "background".parse().unwrap());
+        headers.insert("apns-expiration", "1700000000".parse().unwrap());
+        headers.insert("apns-priority", "10".parse().unwrap());
+        headers.insert("apns-topic", "com.example.app".parse().unwrap());
+        headers.insert("apns-collapse-id", "score".parse().unwrap());
+        headers.insert("x-request-id", "ignored".parse().unwrap());
+
+        let options = NotificationOptions::from_headers(&headers).unwrap();
+
+        assert_eq!(Some("123e4567-e89b-12d3-a456-4266554400a0"), options.apns_id.as_deref());
+        assert_eq!(Some(PushType::Background), options.apns_push_type);
+        assert_eq!(Some(1700000000), options.apns_expiration);
+        assert_eq!(Some(Priority::High), options.apns_priority);
+        assert_eq!(Some("com.example.app"), options.apns_topic.as_deref());
+        assert_eq!(Some("score"), options.apns_collapse_id.as_ref().map(CollapseId::as_str));
+
+        assert_eq!(
+            NotificationOptions::default(),
+            NotificationOptions::from_headers(&http::HeaderMap::new()).unwrap()
+        );
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_options_from_malformed_headers() {
+        let malformed = [
+            ("apns-id", "not-a-uuid"),
+            ("apns-push-type", "carrier-pigeon"),
+            ("apns-expiration", "tomorrow"),
+            ("apns-priority", "1"),
+            ("apns-collapse-id", &"x".repeat(65)),
+        ];
+
+        for (name, value) in malformed {
+            let mut headers = http::HeaderMap::new();
+            headers.insert(name, value.parse().unwrap());
+
+            assert!(
+                matches!(
+                    NotificationOptions::from_headers(&headers),
+                    Err(Error::InvalidOptions(_))
+                ),
+                "{} accepted",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_options_borrow_static_strings() {
+        const TOPIC: &str = "com.example.app";
+
+        let options = NotificationOptions {
+            apns_topic: Some(TOPIC.into()),
+            apns_collapse_id: Some(CollapseId::new("score").unwrap()),
+            ..Default::default()
+        };
+
+        assert!(matches!(options.apns_topic, Some(Cow::Borrowed(TOPIC))));
+        assert!(matches!(
+            options.apns_collapse_id,
+            Some(CollapseId {
+                value: Cow::Borrowed("score")
+            })
+        ));
+
+        let cloned = options.clone();
+
+        assert!(matches!(cloned.apns_topic, Some(Cow::Borrowed(TOPIC))));
+    }
 }
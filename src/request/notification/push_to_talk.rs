@@ -0,0 +1,91 @@
+use crate::request::notification::{NotificationBuilder, NotificationOptions, PushType};
+use crate::request::payload::{Payload, APS};
+use std::borrow::Cow;
+
+/// A builder to create the payload waking a Push to Talk app, e.g. when a
+/// remote participant starts speaking on its channel. The system hands the
+/// payload to the app's channel manager, so it has no `aps` dictionary and
+/// carries only the keys the app reads, added with
+/// [`add_custom_data`](Payload::add_custom_data).
+///
+/// The options get the `pushtotalk` push type unless they set their own.
+/// The client sends the payload with [`Priority::High`](crate::request::notification::Priority::High) and an expiration of
+/// zero, as the notification is stale as soon as it can't be delivered,
+/// unless the options or the
+/// [push type defaults](crate::client::ClientConfig::with_push_type_defaults)
+/// of the client set them. The `apns-topic` must be the bundle ID with the
+/// `.voip-ptt` suffix, see [`PushType::topic`].
+///
+/// # Example
+///
+/// ```rust
+/// # use a2::request::notification::{NotificationBuilder, NotificationOptions, PushToTalkNotificationBuilder, PushType};
+/// # use a2::request::payload::PayloadLike;
+/// # fn main() {
+/// let options = NotificationOptions {
+///     apns_topic: Some(PushType::PushToTalk.topic("com.example.app").into()),
+///     ..Default::default()
+/// };
+/// let mut payload = PushToTalkNotificationBuilder::new().build("token", options);
+/// payload.add_custom_data("speaker", &"Mario").unwrap();
+///
+/// assert_eq!("{\"speaker\":\"Mario\"}", &payload.to_json_string().unwrap());
+/// assert_eq!(Some(PushType::PushToTalk), payload.options.apns_push_type);
+/// assert_eq!(Some(0), payload.default_expiration());
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PushToTalkNotificationBuilder;
+
+impl PushToTalkNotificationBuilder {
+    /// Creates a new builder for a payload without keys.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<'a> NotificationBuilder<'a> for PushToTalkNotificationBuilder {
+    fn build(self, device_token: impl Into<Cow<'a, str>>, mut options: NotificationOptions<'a>) -> Payload<'a> {
+        options.apns_push_type.get_or_insert(PushType::PushToTalk);
+
+        Payload::new(APS::default(), device_token, options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::notification::Priority;
+    use crate::request::payload::PayloadLike;
+
+    #[test]
+    fn test_push_to_talk_options() {
+        let options = NotificationOptions {
+            apns_topic: Some(PushType::PushToTalk.topic("com.example.app").into()),
+            ..Default::default()
+        };
+        let payload = PushToTalkNotificationBuilder::new().build("device-token", options);
+
+        assert_eq!("{}", payload.to_json_string().unwrap());
+        assert_eq!(Some(PushType::PushToTalk), payload.options.apns_push_type);
+        assert_eq!("pushtotalk", payload.options.apns_push_type.unwrap().to_string());
+        assert_eq!(Some("com.example.app.voip-ptt"), payload.options.apns_topic.as_deref());
+        assert_eq!(Some(Priority::High), payload.default_priority());
+        assert_eq!(Some(0), payload.default_expiration());
+        assert_eq!(None, payload.options.apns_priority);
+        assert_eq!(None, payload.options.apns_expiration);
+    }
+
+    #[test]
+    fn test_push_to_talk_keeps_the_given_options() {
+        let options = NotificationOptions {
+            apns_expiration: Some(1700000000),
+            ..Default::default()
+        };
+        let mut payload = PushToTalkNotificationBuilder::new().build("device-token", options);
+        payload.add_custom_data("speaker", &"Luigi").unwrap();
+
+        assert_eq!(Some(1700000000), payload.options.apns_expiration);
+        assert_eq!(json!({"speaker": "Luigi"}), serde_json::to_value(&payload).unwrap());
+    }
+}
@@ -1,9 +1,13 @@
-use std::borrow::Cow;
+use crate::error::Error;
 use crate::request::notification::{NotificationBuilder, NotificationOptions};
 use crate::request::payload::{APSAlert, APSSound, Payload, APS};
-use std::collections::BTreeMap;
+use std::borrow::Cow;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// The alert of a Safari push notification.
+///
+/// The texts are `Cow`s, so an alert built from `&str`s borrows them all the
+/// way to the serialized payload without copying.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "kebab-case")]
 pub struct WebPushAlert<'a> {
     pub title: Cow<'a, str>,
@@ -11,6 +15,31 @@ pub struct WebPushAlert<'a> {
     pub action: Cow<'a, str>,
 }
 
+impl<'a> WebPushAlert<'a> {
+    /// Creates an alert, borrowing the texts given as `&str`.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::WebPushAlert;
+    /// # use std::borrow::Cow;
+    /// # fn main() {
+    /// let alert = WebPushAlert::new("Hello", "World", "View");
+    ///
+    /// assert!(matches!(alert.title, Cow::Borrowed("Hello")));
+    /// # }
+    /// ```
+    pub fn new(
+        title: impl Into<Cow<'a, str>>,
+        body: impl Into<Cow<'a, str>>,
+        action: impl Into<Cow<'a, str>>,
+    ) -> WebPushAlert<'a> {
+        WebPushAlert {
+            title: title.into(),
+            body: body.into(),
+            action: action.into(),
+        }
+    }
+}
+
 /// A builder to create a simple APNs notification payload.
 ///
 /// # Example
@@ -57,7 +86,7 @@ impl<'a> WebNotificationBuilder<'a> {
             sound: None,
             url_args: url_args
                 .as_ref()
-                .into_iter()
+                .iter()
                 .cloned()
                 .map(|arg| arg.into())
                 .collect::<Vec<_>>()
@@ -85,24 +114,104 @@ impl<'a> WebNotificationBuilder<'a> {
         self.sound = Some(sound.into());
         self
     }
+
+    /// Appends an argument to the `url-args`, after the ones given in
+    /// [`new`](Self::new).
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{WebNotificationBuilder, NotificationBuilder, WebPushAlert};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = WebNotificationBuilder::new(WebPushAlert {title: "Hello".into(), body: "World".into(), action: "View".into()}, &["arg1"]);
+    /// builder.add_url_arg("arg2");
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"Hello\",\"body\":\"World\",\"action\":\"View\"},\"url-args\":[\"arg1\",\"arg2\"]}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn add_url_arg(&mut self, arg: impl Into<Cow<'a, str>>) -> &mut Self {
+        self.url_args.to_mut().push(arg.into());
+        self
+    }
+
+    /// Replaces all of the `url-args`.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{WebNotificationBuilder, NotificationBuilder, WebPushAlert};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut builder = WebNotificationBuilder::new(WebPushAlert {title: "Hello".into(), body: "World".into(), action: "View".into()}, &["arg1"]);
+    /// builder.set_url_args(["arg2", "arg3"]);
+    /// let payload = builder.build("token", Default::default());
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":{\"title\":\"Hello\",\"body\":\"World\",\"action\":\"View\"},\"url-args\":[\"arg2\",\"arg3\"]}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_url_args<I>(&mut self, url_args: I) -> &mut Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Cow<'a, str>>,
+    {
+        self.url_args = url_args.into_iter().map(Into::into).collect::<Vec<_>>().into();
+        self
+    }
+
+    /// Replaces all of the `url-args` like [`set_url_args`](Self::set_url_args),
+    /// failing with [`InvalidOptions`](Error::InvalidOptions) if there are no
+    /// arguments while the alert has an action. The arguments fill the
+    /// placeholders of the `urlFormatString` of the website push package,
+    /// which this crate can't check, but an action without any arguments is
+    /// most likely a mistake. Use `set_url_args` for a `urlFormatString`
+    /// without placeholders.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{WebNotificationBuilder, WebPushAlert};
+    /// # fn main() {
+    /// let mut builder = WebNotificationBuilder::new(WebPushAlert::new("Hello", "World", "View"), &["arg1"]);
+    ///
+    /// assert!(builder.try_set_url_args(["arg2"]).is_ok());
+    /// assert!(builder.try_set_url_args(Vec::<&str>::new()).is_err());
+    /// # }
+    /// ```
+    pub fn try_set_url_args<I>(&mut self, url_args: I) -> Result<&mut Self, Error>
+    where
+        I: IntoIterator,
+        I::Item: Into<Cow<'a, str>>,
+    {
+        let url_args: Vec<Cow<'a, str>> = url_args.into_iter().map(Into::into).collect();
+
+        if url_args.is_empty() && !self.alert.action.is_empty() {
+            return Err(Error::InvalidOptions(format!(
+                "The action {} has no url-args for the urlFormatString",
+                self.alert.action
+            )));
+        }
+
+        self.url_args = url_args.into();
+        Ok(self)
+    }
 }
 
 impl<'a> NotificationBuilder<'a> for WebNotificationBuilder<'a> {
     fn build(self, device_token: impl Into<Cow<'a, str>>, options: NotificationOptions<'a>) -> Payload<'a> {
-        Payload {
-            aps: APS {
-                alert: Some(APSAlert::WebPush(self.alert)),
-                badge: None,
-                sound: self.sound.map(APSSound::Sound),
-                content_available: None,
-                category: None,
-                mutable_content: None,
-                url_args: Some(self.url_args),
-            },
-            device_token: device_token.into(),
-            options,
-            data: BTreeMap::new(),
-        }
+        let aps = APS {
+            alert: Some(APSAlert::WebPush(self.alert)),
+            badge: None,
+            sound: self.sound.map(APSSound::Sound),
+            content_available: None,
+            category: None,
+            mutable_content: None,
+            url_args: Some(self.url_args),
+            ..Default::default()
+        };
+
+        Payload::new(aps, device_token, options)
     }
 }
 
@@ -120,7 +229,7 @@ mod tests {
                 title: "Hello".into(),
                 body: "world".into(),
             },
-            &["arg1"],
+            ["arg1"],
         )
         .build("device-token", Default::default())
         .to_json_string()
@@ -139,4 +248,69 @@ mod tests {
 
         assert_eq!(expected_payload, serde_json::from_str::<Value>(&payload).unwrap());
     }
+
+    #[test]
+    fn test_webpush_appended_url_args() {
+        let mut builder = WebNotificationBuilder::new(
+            WebPushAlert {
+                action: "View".into(),
+                title: "Hello".into(),
+                body: "world".into(),
+            },
+            Vec::<&str>::new(),
+        );
+        builder.add_url_arg("arg1").add_url_arg(String::from("arg2"));
+
+        let payload = builder
+            .build("device-token", Default::default())
+            .to_json_string()
+            .unwrap();
+        let payload = serde_json::from_str::<Value>(&payload).unwrap();
+
+        assert_eq!(json!(["arg1", "arg2"]), payload["aps"]["url-args"]);
+    }
+
+    #[test]
+    fn test_webpush_borrows_the_texts() {
+        let title = String::from("Hello");
+        let args = [String::from("arg1")];
+
+        let mut builder = WebNotificationBuilder::new(WebPushAlert::new(title.as_str(), "world", "View"), ["arg0"]);
+        builder.add_url_arg(args[0].as_str()).set_sound("ping.aiff");
+
+        let payload = builder.build("device-token", Default::default());
+
+        match payload.aps.alert {
+            Some(APSAlert::WebPush(ref alert)) => {
+                assert!(matches!(alert.title, Cow::Borrowed(_)));
+                assert!(matches!(alert.body, Cow::Borrowed(_)));
+                assert!(matches!(alert.action, Cow::Borrowed(_)));
+            }
+            ref alert => panic!("expected a web push alert, got {:?}", alert),
+        }
+
+        let url_args = payload.aps.url_args.as_deref().unwrap();
+        assert!(url_args.iter().all(|arg| matches!(arg, Cow::Borrowed(_))));
+        assert!(matches!(payload.aps.sound, Some(APSSound::Sound(Cow::Borrowed(_)))));
+    }
+
+    #[test]
+    fn test_action_without_url_args_is_flagged() {
+        let mut builder = WebNotificationBuilder::new(WebPushAlert::new("Hello", "world", "View"), ["arg1"]);
+
+        assert!(matches!(
+            builder.try_set_url_args(Vec::<String>::new()),
+            Err(Error::InvalidOptions(_))
+        ));
+        assert!(builder.try_set_url_args(["arg2"]).is_ok());
+
+        let payload = builder.build("device-token", Default::default());
+        assert_eq!(
+            json!(["arg2"]),
+            serde_json::to_value(&payload).unwrap()["aps"]["url-args"]
+        );
+
+        let mut builder = WebNotificationBuilder::new(WebPushAlert::new("Hello", "world", ""), ["arg1"]);
+        assert!(builder.try_set_url_args(Vec::<String>::new()).is_ok());
+    }
 }
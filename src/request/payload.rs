@@ -1,14 +1,47 @@
 /// Payload with `aps` and custom data
 use crate::error::Error;
-use crate::request::notification::{DefaultAlert, DefaultSound, NotificationOptions, WebPushAlert};
+use crate::request::notification::{
+    DefaultAlert, DefaultSound, InterruptionLevel, LiveActivityEvent, NotificationOptions, Priority, PushType,
+    WebPushAlert,
+};
+use crate::request::validation::{self, ValidationIssue};
 use erased_serde::Serialize;
+use serde::ser::{Error as _, SerializeMap};
+use serde_json::ser::{CompactFormatter, Formatter};
+use serde_json::value::RawValue;
 use serde_json::{self, Value};
 use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+use std::io;
+
+/// The maximum size of a notification payload in bytes.
+pub const MAX_PAYLOAD_SIZE: usize = 4096;
+
+/// The maximum size of a VoIP notification payload in bytes.
+pub const MAX_VOIP_PAYLOAD_SIZE: usize = 5120;
+
+/// The custom data of a [`Payload`], serialized sorted by key. With the
+/// `preserve_order` feature it is an `IndexMap` serializing the keys in
+/// the order they were inserted in; the keys of nested objects stay sorted.
+pub type CustomData<'a> = DataMap<Cow<'a, str>, Value>;
+
+#[cfg(not(feature = "preserve_order"))]
+type DataMap<K, V> = BTreeMap<K, V>;
+
+#[cfg(feature = "preserve_order")]
+type DataMap<K, V> = indexmap::IndexMap<K, V>;
 
 /// The data and options for a push notification.
-#[derive(Debug, Clone, Serialize)]
+///
+/// Two payloads are equal when they target the same device token with the
+/// same options, `aps` dictionary and custom data as serialized, which makes
+/// it possible to deduplicate payloads in a `HashSet` before sending.
+///
+/// A payload can be deserialized from its JSON form, in which case the device
+/// token and options are left empty and have to be set separately.
+#[derive(Debug, Clone, Deserialize)]
 pub struct Payload<'a> {
     /// Send options
     #[serde(skip)]
@@ -16,11 +49,21 @@ pub struct Payload<'a> {
     /// The token for the receiving device
     #[serde(skip)]
     pub device_token: Cow<'a, str>,
-    /// The pre-defined notification payload
+    /// The pre-defined notification payload, left out when empty as in an
+    /// MDM payload
+    #[serde(default, skip_serializing_if = "APS::is_empty")]
     pub aps: APS<'a>,
-    /// Application specific payload
+    /// Application specific payload, see [`CustomData`] for the order of
+    /// its keys
     #[serde(flatten)]
-    pub data: BTreeMap<Cow<'a, str>, Value>,
+    pub data: CustomData<'a>,
+    /// Keys of `data` that may be removed, in this order, if the serialized
+    /// payload would otherwise exceed the size limit
+    #[serde(skip)]
+    droppable_data: Vec<Cow<'a, str>>,
+    /// The case the keys of `data` are converted to when serialized
+    #[serde(skip)]
+    key_case: Option<KeyCase>,
 }
 
 /// Object that can be serialized to create an APNS request.
@@ -29,17 +72,19 @@ pub struct Payload<'a> {
 /// # Example
 /// ```no_run
 /// use std::borrow::Cow;
-/// use a2::request::notification::{NotificationBuilder, NotificationOptions};
+/// use a2::request::notification::{CategoryId, NotificationBuilder, NotificationOptions};
 /// use a2::request::payload::{PayloadLike, APS};
+/// # #[cfg(feature = "client")]
 /// use a2::{Client, ClientConfig, DefaultNotificationBuilder, Endpoint};
 /// use serde::Serialize;
 /// use std::fs::File;
 ///
+/// # #[cfg(feature = "client")]
 /// async fn send() -> Result<(), Box<dyn std::error::Error>> {
 ///     let builder = DefaultNotificationBuilder::new()
 ///         .set_body("Hi there")
 ///         .set_badge(420)
-///         .set_category("cat1")
+///         .set_category(CategoryId::new("cat1")?)
 ///         .set_sound("ping.flac");
 ///
 ///     let payload = builder.build("device-token-from-the-user", Default::default());
@@ -66,7 +111,7 @@ pub struct Payload<'a> {
 ///     fn get_device_token(&self) -> Cow<'a, str> {
 ///         self.device_token.clone()
 ///     }
-///     fn get_options(&self) -> &NotificationOptions {
+///     fn get_options(&self) -> &NotificationOptions<'_> {
 ///         &self.options
 ///     }
 /// }
@@ -79,24 +124,475 @@ pub trait PayloadLike: serde::Serialize + Debug {
         Ok(serde_json::to_string(&self)?)
     }
 
+    /// Writes the JSON of [`to_json_string`](Self::to_json_string) to the
+    /// writer, e.g. into a reused buffer. Returns an error if serialization
+    /// or writing fails.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .build("token", Default::default());
+    /// let mut buffer = Vec::new();
+    /// payload.write_json(&mut buffer).unwrap();
+    ///
+    /// assert_eq!(payload.to_json_string().unwrap().as_bytes(), &buffer[..]);
+    /// # }
+    /// ```
+    fn write_json<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        let json = self.to_json_string()?;
+
+        writer.write_all(json.as_bytes()).map_err(serde_json::Error::io)?;
+        Ok(())
+    }
+
+    /// The size of the serialized payload in bytes, counted without
+    /// allocating the JSON string. Returns an error if serialization fails.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::{PayloadLike, MAX_PAYLOAD_SIZE};
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .build("token", Default::default());
+    ///
+    /// assert_eq!(46, payload.size_bytes().unwrap());
+    /// assert_eq!(4050, MAX_PAYLOAD_SIZE - payload.size_bytes().unwrap());
+    /// # }
+    /// ```
+    fn size_bytes(&self) -> Result<usize, Error> {
+        let mut counter = ByteCounter(0);
+        serde_json::to_writer(&mut counter, &self)?;
+
+        Ok(counter.0)
+    }
+
+    /// Checks the payload for content that is inconsistent, returning an
+    /// [`InvalidPayload`](Error::InvalidPayload) error describing the first
+    /// problem found. Accepts everything by default.
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// The `apns-priority` sent when neither the options nor the
+    /// [push type defaults](crate::client::ClientConfig::with_push_type_defaults)
+    /// of the client set one, e.g. the
+    /// [priority of a Live Activity event](LiveActivityEvent::default_priority).
+    /// None by default, which leaves the header out.
+    fn default_priority(&self) -> Option<Priority> {
+        None
+    }
+
+    /// The `apns-expiration` sent when neither the options nor the push type
+    /// defaults of the client set one, e.g. zero for a
+    /// [Push to Talk](crate::request::notification::PushToTalkNotificationBuilder)
+    /// payload. None by default, which leaves the header out.
+    fn default_expiration(&self) -> Option<u64> {
+        None
+    }
+
     /// Returns token for the device
     fn get_device_token(&self) -> Cow<'_, str>;
 
     /// Gets [`NotificationOptions`] for this Payload.
-    fn get_options(&self) -> &NotificationOptions;
+    fn get_options(&self) -> &NotificationOptions<'_>;
 }
 
 impl<'a> PayloadLike for Payload<'a> {
+    /// Serializes the payload, removing the custom data added with
+    /// [`add_droppable_custom_data`](Payload::add_droppable_custom_data) one
+    /// key at a time until the payload fits the size limit. If the payload is
+    /// still too large after dropping everything droppable, the smallest
+    /// version is returned and the client will refuse to send it.
+    fn to_json_string(&self) -> Result<String, Error> {
+        self.to_compacted_json(|payload| Ok(serde_json::to_string(payload)?))
+    }
+
+    /// Serializes straight into the writer, unless the payload has droppable
+    /// custom data, which needs the size of the whole payload first.
+    fn write_json<W: io::Write>(&self, writer: &mut W) -> Result<(), Error> {
+        if !self.droppable_data.is_empty() {
+            writer
+                .write_all(self.to_json_string()?.as_bytes())
+                .map_err(serde_json::Error::io)?;
+            return Ok(());
+        }
+
+        serde_json::to_writer(writer, self)?;
+        Ok(())
+    }
+
+    /// Counts the bytes [`to_json_string`](PayloadLike::to_json_string)
+    /// returns, after dropping the droppable custom data that doesn't fit.
+    fn size_bytes(&self) -> Result<usize, Error> {
+        self.to_compacted(
+            |payload| {
+                let mut counter = ByteCounter(0);
+                serde_json::to_writer(&mut counter, payload)?;
+
+                Ok(counter.0)
+            },
+            |size| *size,
+        )
+    }
+
+    /// Flags a critical sound without the `critical` interruption level.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, InterruptionLevel, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let inconsistent = DefaultNotificationBuilder::new()
+    ///     .set_critical(true, None)
+    ///     .set_interruption_level(InterruptionLevel::Active)
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(inconsistent.validate().is_err());
+    ///
+    /// let critical = DefaultNotificationBuilder::new()
+    ///     .set_critical_alert(None)
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(critical.validate().is_ok());
+    /// # }
+    /// ```
+    fn validate(&self) -> Result<(), Error> {
+        match validation::conflicts(&self.aps).into_iter().next() {
+            Some(issue) => Err(Error::InvalidPayload(issue.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// The [default priority](LiveActivityEvent::default_priority) of the
+    /// Live Activity event, or [`Priority::High`] for Push to Talk.
+    fn default_priority(&self) -> Option<Priority> {
+        match self.aps.event {
+            Some(event) => Some(event.default_priority()),
+            None if self.is_push_to_talk() => Some(Priority::High),
+            None => None,
+        }
+    }
+
+    /// Zero for Push to Talk, as the notification is stale as soon as it
+    /// can't be delivered.
+    fn default_expiration(&self) -> Option<u64> {
+        if self.is_push_to_talk() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
     fn get_device_token(&self) -> Cow<'a, str> {
         self.device_token.clone()
     }
 
-    fn get_options(&self) -> &NotificationOptions {
+    fn get_options(&self) -> &NotificationOptions<'_> {
         &self.options
     }
 }
 
 impl<'a> Payload<'a> {
+    fn is_push_to_talk(&self) -> bool {
+        self.options.apns_push_type == Some(PushType::PushToTalk)
+    }
+
+    /// Serializes with `to_json`, dropping the droppable custom data until
+    /// the payload fits the size limit.
+    fn to_compacted_json<F>(&self, to_json: F) -> Result<String, Error>
+    where
+        F: Fn(&Payload<'a>) -> Result<String, Error>,
+    {
+        self.to_compacted(to_json, String::len)
+    }
+
+    /// Serializes with `serialize` like
+    /// [`to_compacted_json`](Self::to_compacted_json), taking the size of
+    /// every output from `len`.
+    fn to_compacted<T, F, L>(&self, serialize: F, len: L) -> Result<T, Error>
+    where
+        F: Fn(&Payload<'a>) -> Result<T, Error>,
+        L: Fn(&T) -> usize,
+    {
+        let output = serialize(self)?;
+        let limit = max_payload_size(&self.options);
+
+        if len(&output) <= limit || self.droppable_data.is_empty() {
+            return Ok(output);
+        }
+
+        let mut compacted = self.clone();
+        let mut output = output;
+
+        for key in self.droppable_data.iter() {
+            if compacted.remove_data(key).is_none() {
+                continue;
+            }
+
+            output = serialize(&compacted)?;
+
+            if len(&output) <= limit {
+                break;
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+/// A [`Payload`] writing the values of its custom data with a custom
+/// `serde_json` [`Formatter`], e.g. for a fixed number format a legacy
+/// system compares byte for byte. The `aps` dictionary and the keys are
+/// written as usual, the same way when sent and when serialized with
+/// `serde_json`. Created with [`Payload::with_data_formatter`].
+#[derive(Clone)]
+pub struct FormattedPayload<'a, F> {
+    payload: Payload<'a>,
+    formatter: F,
+}
+
+impl<'a> Payload<'a> {
+    /// Writes the values of the custom data with a clone of the given
+    /// formatter when sent.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # use serde_json::ser::Formatter;
+    /// # use std::io;
+    /// #[derive(Clone)]
+    /// struct TwoDecimals;
+    ///
+    /// impl Formatter for TwoDecimals {
+    ///     fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+    ///         write!(writer, "{:.2}", value)
+    ///     }
+    /// }
+    ///
+    /// # fn main() {
+    /// let mut payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .build("token", Default::default());
+    /// payload.add_custom_data("price", &4.5).unwrap();
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":\"a body\",\"mutable-content\":0},\"price\":4.50}",
+    ///     &payload.with_data_formatter(TwoDecimals).to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn with_data_formatter<F: Formatter + Clone>(self, formatter: F) -> FormattedPayload<'a, F> {
+        FormattedPayload {
+            payload: self,
+            formatter,
+        }
+    }
+}
+
+impl<'a, F: Formatter + Clone> FormattedPayload<'a, F> {
+    /// The wrapped payload.
+    pub fn payload(&self) -> &Payload<'a> {
+        &self.payload
+    }
+
+    fn formatted_json(&self, payload: &Payload<'a>) -> Result<String, Error> {
+        let mut json = Vec::new();
+        payload.serialize_formatted(&mut serde_json::Serializer::new(&mut json), Some(&self.formatter))?;
+
+        String::from_utf8(json).map_err(|e| Error::InvalidPayload(e.to_string()))
+    }
+}
+
+impl<'a, F> Debug for FormattedPayload<'a, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FormattedPayload")
+            .field("payload", &self.payload)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, F: Formatter + Clone> serde::Serialize for FormattedPayload<'a, F> {
+    /// Serializes like the wrapped payload, with the values of the custom
+    /// data pre-formatted as raw JSON, which only `serde_json` writes as is.
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.payload.serialize_formatted(serializer, Some(&self.formatter))
+    }
+}
+
+impl<'a, F: Formatter + Clone> PayloadLike for FormattedPayload<'a, F> {
+    fn to_json_string(&self) -> Result<String, Error> {
+        self.payload.to_compacted_json(|payload| self.formatted_json(payload))
+    }
+
+    fn size_bytes(&self) -> Result<usize, Error> {
+        Ok(self.to_json_string()?.len())
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        self.payload.validate()
+    }
+
+    fn default_priority(&self) -> Option<Priority> {
+        self.payload.default_priority()
+    }
+
+    fn default_expiration(&self) -> Option<u64> {
+        self.payload.default_expiration()
+    }
+
+    fn get_device_token(&self) -> Cow<'_, str> {
+        self.payload.get_device_token()
+    }
+
+    fn get_options(&self) -> &NotificationOptions<'_> {
+        self.payload.get_options()
+    }
+}
+
+impl<'a> serde::Serialize for Payload<'a> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize_formatted(serializer, None::<&CompactFormatter>)
+    }
+}
+
+impl<'a> Payload<'a> {
+    /// Serializes the `aps` dictionary and the custom data, writing the
+    /// values of the custom data with the formatter if one is given.
+    fn serialize_formatted<S, F>(&self, serializer: S, formatter: Option<&F>) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+        F: Formatter + Clone,
+    {
+        let mut map = serializer.serialize_map(None)?;
+
+        if !self.aps.is_empty() {
+            map.serialize_entry("aps", &self.aps)?;
+        }
+
+        for (key, value) in self.serialized_data() {
+            match formatter {
+                Some(formatter) => {
+                    let formatted = format_value(&value, formatter.clone()).map_err(S::Error::custom)?;
+                    map.serialize_entry(&key, &formatted)?;
+                }
+                None => map.serialize_entry(&key, &value)?,
+            }
+        }
+
+        map.end()
+    }
+}
+
+/// The value written with the formatter, as raw JSON to embed as is.
+fn format_value<F: Formatter>(value: &Value, formatter: F) -> Result<Box<RawValue>, serde_json::Error> {
+    let mut json = Vec::new();
+    serde::Serialize::serialize(value, &mut serde_json::Serializer::with_formatter(&mut json, formatter))?;
+
+    let json = String::from_utf8(json).map_err(serde::ser::Error::custom)?;
+    RawValue::from_string(json)
+}
+
+impl<'a> PartialEq for Payload<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.device_token == other.device_token
+            && self.options == other.options
+            && self.aps == other.aps
+            && self.serialized_data() == other.serialized_data()
+    }
+}
+
+impl<'a> Eq for Payload<'a> {}
+
+impl<'a> Hash for Payload<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.device_token.hash(state);
+        self.options.hash(state);
+        self.aps.hash(state);
+        self.serialized_data().hash(state);
+    }
+}
+
+impl<'a> Payload<'a> {
+    /// A payload with the given `aps` dictionary and without custom data,
+    /// for an `aps` the builders don't cover. The builders are the easier
+    /// way for all other payloads.
+    ///
+    /// ```rust
+    /// # use a2::request::payload::{Payload, PayloadLike, APS};
+    /// # fn main() {
+    /// let aps = APS {
+    ///     content_available: Some(1),
+    ///     ..Default::default()
+    /// };
+    /// let payload = Payload::new(aps, "token", Default::default());
+    ///
+    /// assert_eq!("{\"aps\":{\"content-available\":1}}", &payload.to_json_string().unwrap());
+    /// # }
+    /// ```
+    pub fn new(aps: APS<'a>, device_token: impl Into<Cow<'a, str>>, options: NotificationOptions<'a>) -> Self {
+        Payload {
+            options,
+            device_token: device_token.into(),
+            aps,
+            data: CustomData::new(),
+            droppable_data: Vec::new(),
+            key_case: None,
+        }
+    }
+
+    /// A notification only setting the app icon badge, without an alert, a
+    /// sound or `content-available`. Uses the `alert` push type unless the
+    /// options set another one, as a badge change is user-visible and the
+    /// `background` push type can't carry one.
+    ///
+    /// ```rust
+    /// # use a2::request::payload::{Payload, PayloadLike};
+    /// # fn main() {
+    /// let payload = Payload::badge_only("token", 3, Default::default());
+    ///
+    /// assert_eq!("{\"aps\":{\"badge\":3}}", &payload.to_json_string().unwrap());
+    /// # }
+    /// ```
+    pub fn badge_only(
+        device_token: impl Into<Cow<'a, str>>,
+        badge: u32,
+        mut options: NotificationOptions<'a>,
+    ) -> Payload<'a> {
+        options.apns_push_type.get_or_insert(PushType::Alert);
+
+        let aps = APS {
+            badge: Some(badge),
+            ..Default::default()
+        };
+
+        Payload::new(aps, device_token, options)
+    }
+
+    /// Checks a JSON payload from another system against the format APNs
+    /// expects without sending it, listing every problem found: JSON that
+    /// is not an object, a missing `aps` dictionary, unknown or mistyped
+    /// `aps` keys, conflicting keys and a size over the limit of regular
+    /// notifications.
+    ///
+    /// ```rust
+    /// # use a2::request::payload::Payload;
+    /// # use a2::request::validation::ValidationIssue;
+    /// # fn main() {
+    /// assert!(Payload::validate_json(r#"{"aps":{"alert":"Hi"}}"#).is_ok());
+    ///
+    /// assert_eq!(
+    ///     Err(vec![ValidationIssue::UnknownApsKey(String::from("allert"))]),
+    ///     Payload::validate_json(r#"{"aps":{"allert":"Hi"}}"#)
+    /// );
+    /// # }
+    /// ```
+    pub fn validate_json(json: &str) -> Result<(), Vec<ValidationIssue>> {
+        validation::validate_json(json)
+    }
+
     /// Client-specific custom data to be added in the payload.
     /// The `root_key` defines the JSON key in the root of the request
     /// data, and `data` the object containing custom data. The `data`
@@ -112,7 +608,7 @@ impl<'a> Payload<'a> {
     /// # use a2::request::payload::PayloadLike;
     /// # fn main() {
     /// let mut payload = DefaultNotificationBuilder::new()
-    ///     .set_content_available()
+    ///     .set_content_available(true)
     ///     .build("token", Default::default());
     /// let mut custom_data = HashMap::new();
     ///
@@ -139,7 +635,7 @@ impl<'a> Payload<'a> {
     /// }
     ///
     /// let mut payload = DefaultNotificationBuilder::new()
-    ///     .set_content_available()
+    ///     .set_content_available(true)
     ///     .build("token", Default::default());
     /// let mut custom_data = CompanyData { foo: "bar" };
     ///
@@ -151,15 +647,293 @@ impl<'a> Payload<'a> {
     /// );
     /// }
     /// ```
-    pub fn add_custom_data(&mut self, root_key: impl Into<Cow<'a, str>>, data: &dyn Serialize) -> Result<&mut Self, Error> {
-        self.data.insert(root_key.into(), serde_json::to_value(data)?);
+    pub fn add_custom_data(
+        &mut self,
+        root_key: impl Into<Cow<'a, str>>,
+        data: &dyn Serialize,
+    ) -> Result<&mut Self, Error> {
+        let root_key = root_key.into();
+
+        self.data.insert(root_key.clone(), serde_json::to_value(data)?);
+        self.droppable_data.retain(|key| key != &root_key);
+
+        Ok(self)
+    }
+
+    /// Adds custom data like [`add_custom_data`](Payload::add_custom_data),
+    /// but marks it as optional until it is added again with
+    /// `add_custom_data`: if the serialized payload is over the size
+    /// limit, droppable keys are removed in the order they were added until
+    /// the payload fits. This keeps the visible alert intact at the cost of
+    /// optional metadata.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::PayloadLike;
+    /// # fn main() {
+    /// let mut payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .build("token", Default::default());
+    ///
+    /// payload.add_custom_data("id", &42).unwrap();
+    /// payload.add_droppable_custom_data("debug", &"x".repeat(5000)).unwrap();
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":\"a body\",\"mutable-content\":0},\"id\":42}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn add_droppable_custom_data(
+        &mut self,
+        root_key: impl Into<Cow<'a, str>>,
+        data: &dyn Serialize,
+    ) -> Result<&mut Self, Error> {
+        let root_key = root_key.into();
+
+        self.data.insert(root_key.clone(), serde_json::to_value(data)?);
+        self.droppable_data.retain(|key| key != &root_key);
+        self.droppable_data.push(root_key);
 
         Ok(self)
     }
+
+    /// Removes a key of the custom data, keeping the order of the others.
+    #[cfg(feature = "preserve_order")]
+    fn remove_data(&mut self, key: &str) -> Option<Value> {
+        self.data.shift_remove(key)
+    }
+
+    #[cfg(not(feature = "preserve_order"))]
+    fn remove_data(&mut self, key: &str) -> Option<Value> {
+        self.data.remove(key)
+    }
+
+    /// The custom data the way it is serialized, with the keys converted to
+    /// the key case. Of two keys converting to the same name, the later one
+    /// in the order of the custom data is kept.
+    fn serialized_data(&self) -> Vec<(Cow<'_, str>, Cow<'_, Value>)> {
+        let ordered = self.data.iter();
+
+        let Some(case) = self.key_case else {
+            return ordered
+                .map(|(key, value)| (Cow::Borrowed(key.as_ref()), Cow::Borrowed(value)))
+                .collect();
+        };
+
+        let mut converted: Vec<(Cow<'_, str>, Cow<'_, Value>)> = Vec::new();
+
+        for (key, value) in ordered {
+            let key = case.apply(key);
+
+            converted.retain(|(converted_key, _)| *converted_key != key);
+            converted.push((Cow::Owned(key), Cow::Owned(case.apply_nested(value.clone()))));
+        }
+
+        if !cfg!(feature = "preserve_order") {
+            converted.sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
+
+        converted
+    }
+
+    /// Marks the given keys of the custom data as droppable, in the order
+    /// they are dropped in.
+    pub(crate) fn set_droppable_data(&mut self, keys: Vec<Cow<'a, str>>) {
+        self.droppable_data = keys;
+    }
+
+    /// The keys of the custom data added with
+    /// [`add_droppable_custom_data`](Payload::add_droppable_custom_data), in
+    /// the order they are dropped in.
+    pub fn droppable_data(&self) -> &[Cow<'a, str>] {
+        &self.droppable_data
+    }
+
+    /// Converts the keys of the custom data, and of the objects nested in
+    /// it, to the given case when the payload is serialized, e.g. to match
+    /// the naming of the app when the data comes from Rust structs. The
+    /// `aps` dictionary is left as is, and so are the keys stored in
+    /// [`data`](Payload::data). Of two keys that end up with the same name,
+    /// the later one in the order of the custom data is kept. Also set by
+    /// [`DefaultNotificationBuilder::set_custom_data_key_case`](crate::request::notification::DefaultNotificationBuilder::set_custom_data_key_case).
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use a2::request::payload::{KeyCase, PayloadLike};
+    /// # use serde_json::json;
+    /// # fn main() {
+    /// let mut payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .build("token", Default::default());
+    ///
+    /// payload
+    ///     .set_custom_data_key_case(Some(KeyCase::SnakeCase))
+    ///     .add_custom_data("matchId", &json!({ "otherUser": 7 }))
+    ///     .unwrap();
+    ///
+    /// assert_eq!(
+    ///     "{\"aps\":{\"alert\":\"a body\",\"mutable-content\":0},\"match_id\":{\"other_user\":7}}",
+    ///     &payload.to_json_string().unwrap()
+    /// );
+    /// # }
+    /// ```
+    pub fn set_custom_data_key_case(&mut self, case: Option<KeyCase>) -> &mut Self {
+        self.key_case = case;
+        self
+    }
+
+    /// The case the keys of the custom data are converted to when
+    /// serialized, if any.
+    pub fn custom_data_key_case(&self) -> Option<KeyCase> {
+        self.key_case
+    }
+
+    /// True if the payload stays within the size limit with the keys of
+    /// `extra` added to the custom data, checked without changing the
+    /// payload. `extra` has to serialize to a JSON object, its keys replace
+    /// equal keys of the custom data like
+    /// [`add_custom_data`](Payload::add_custom_data) would. Droppable custom
+    /// data counts towards the size.
+    ///
+    /// ```rust
+    /// # use a2::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    /// # use serde_json::json;
+    /// # fn main() {
+    /// let payload = DefaultNotificationBuilder::new()
+    ///     .set_body("a body")
+    ///     .build("token", Default::default());
+    ///
+    /// assert!(payload.can_fit(&json!({ "order": 1234 })));
+    /// assert!(!payload.can_fit(&json!({ "details": "x".repeat(5000) })));
+    /// # }
+    /// ```
+    pub fn can_fit(&self, extra: &dyn Serialize) -> bool {
+        let Ok(Value::Object(extra)) = serde_json::to_value(extra) else {
+            return false;
+        };
+
+        let mut merged = self.clone();
+        merged
+            .data
+            .extend(extra.into_iter().map(|(key, value)| (Cow::Owned(key), value)));
+
+        matches!(merged.size_bytes(), Ok(size) if size <= max_payload_size(&merged.options))
+    }
+}
+
+/// Counts the bytes written to it.
+struct ByteCounter(usize);
+
+impl io::Write for ByteCounter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A naming convention for the keys of the custom data, see
+/// [`Payload::set_custom_data_key_case`]. Keys are split into words at `_`,
+/// `-` and spaces and where lowercase changes to uppercase, so any of the
+/// conventions converts into the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCase {
+    /// `matchId`
+    CamelCase,
+    /// `match_id`
+    SnakeCase,
+    /// `match-id`
+    KebabCase,
+}
+
+impl KeyCase {
+    fn apply(self, key: &str) -> String {
+        let words = split_words(key);
+
+        match self {
+            KeyCase::CamelCase => words
+                .iter()
+                .enumerate()
+                .map(|(i, word)| if i == 0 { word.clone() } else { capitalize(word) })
+                .collect(),
+            KeyCase::SnakeCase => words.join("_"),
+            KeyCase::KebabCase => words.join("-"),
+        }
+    }
+
+    fn apply_nested(self, value: Value) -> Value {
+        match value {
+            Value::Object(map) => Value::Object(
+                map.into_iter()
+                    .map(|(key, value)| (self.apply(&key), self.apply_nested(value)))
+                    .collect(),
+            ),
+            Value::Array(values) => Value::Array(values.into_iter().map(|value| self.apply_nested(value)).collect()),
+            value => value,
+        }
+    }
+}
+
+/// The lowercase words of a key, e.g. `["http", "server", "id"]` for
+/// `HTTPServer_id`.
+fn split_words(key: &str) -> Vec<String> {
+    let chars: Vec<char> = key.chars().collect();
+    let mut words = Vec::new();
+    let mut word = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if c == '_' || c == '-' || c == ' ' {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+
+        let previous = if i > 0 { chars.get(i - 1) } else { None };
+        let next = chars.get(i + 1);
+        let starts_word = c.is_uppercase()
+            && previous.map_or(false, |p| {
+                p.is_lowercase() || p.is_ascii_digit() || (p.is_uppercase() && next.map_or(false, |n| n.is_lowercase()))
+            });
+
+        if starts_word && !word.is_empty() {
+            words.push(std::mem::take(&mut word));
+        }
+
+        word.extend(c.to_lowercase());
+    }
+
+    if !word.is_empty() {
+        words.push(word);
+    }
+
+    words
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// The payload size limit APNs enforces for a notification with the given
+/// options.
+pub(crate) fn max_payload_size(options: &NotificationOptions<'_>) -> usize {
+    match options.apns_push_type {
+        Some(PushType::Voip) => MAX_VOIP_PAYLOAD_SIZE,
+        _ => MAX_PAYLOAD_SIZE,
+    }
 }
 
 /// The pre-defined notification data.
-#[derive(Serialize, Default, Debug, Clone)]
+#[derive(Serialize, Deserialize, Default, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(rename_all = "kebab-case")]
 #[allow(clippy::upper_case_acronyms)]
 pub struct APS<'a> {
@@ -191,22 +965,91 @@ pub struct APS<'a> {
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub url_args: Option<Cow<'a, [Cow<'a, str>]>>,
+
+    /// How strongly the notification interrupts the user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interruption_level: Option<InterruptionLevel>,
+
+    /// When a Live Activity update was generated, in seconds since the Unix
+    /// epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+
+    /// What a Live Activity notification does to the activity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub event: Option<LiveActivityEvent>,
+
+    /// The new state of a Live Activity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_state: Option<Value>,
+
+    /// When an ended Live Activity is removed, in seconds since the Unix
+    /// epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dismissal_date: Option<u64>,
+
+    /// When the state of a Live Activity becomes outdated, in seconds since
+    /// the Unix epoch.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stale_date: Option<u64>,
+
+    /// Keys of the `aps` dictionary this crate does not model yet, serialized
+    /// next to the other `aps` keys.
+    #[serde(flatten)]
+    pub extra: BTreeMap<Cow<'a, str>, Value>,
+}
+
+impl<'a> APS<'a> {
+    /// The `aps` keys with a typed field, which can't be set as extra keys.
+    pub const KEYS: [&'static str; 13] = [
+        "alert",
+        "badge",
+        "sound",
+        "content-available",
+        "category",
+        "mutable-content",
+        "url-args",
+        "interruption-level",
+        "timestamp",
+        "event",
+        "content-state",
+        "dismissal-date",
+        "stale-date",
+    ];
+
+    /// True if none of the `aps` keys is set.
+    pub fn is_empty(&self) -> bool {
+        self.alert.is_none()
+            && self.badge.is_none()
+            && self.sound.is_none()
+            && self.content_available.is_none()
+            && self.category.is_none()
+            && self.mutable_content.is_none()
+            && self.url_args.is_none()
+            && self.interruption_level.is_none()
+            && self.timestamp.is_none()
+            && self.event.is_none()
+            && self.content_state.is_none()
+            && self.dismissal_date.is_none()
+            && self.stale_date.is_none()
+            && self.extra.is_empty()
+    }
 }
 
 /// Different notification content types.
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(untagged)]
 pub enum APSAlert<'a> {
-    /// A notification that supports all of the iOS features
-    Default(DefaultAlert<'a>),
     /// Safari web push notification
     WebPush(WebPushAlert<'a>),
+    /// A notification that supports all of the iOS features
+    Default(DefaultAlert<'a>),
     /// A notification with just a body
     Body(Cow<'a, str>),
 }
 
 /// Different notification sound types.
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 #[serde(untagged)]
 pub enum APSSound<'a> {
     /// A critical notification (supported only on >= iOS 12)
@@ -214,3 +1057,458 @@ pub enum APSSound<'a> {
     /// Name for a notification sound
     Sound(Cow<'a, str>),
 }
+
+impl<'a> APSSound<'a> {
+    /// The sound name that plays the system default sound.
+    pub const DEFAULT_SYSTEM: &'static str = "default";
+
+    /// The system default sound, serialized as `"sound":"default"`.
+    ///
+    /// ```rust
+    /// # use a2::request::payload::APSSound;
+    /// # fn main() {
+    /// assert_eq!("\"default\"", serde_json::to_string(&APSSound::default_system()).unwrap());
+    /// # }
+    /// ```
+    pub fn default_system() -> APSSound<'static> {
+        APSSound::Sound(Cow::Borrowed(Self::DEFAULT_SYSTEM))
+    }
+
+    /// True for the system default sound.
+    pub fn is_default_system(&self) -> bool {
+        matches!(self, APSSound::Sound(name) if name == Self::DEFAULT_SYSTEM)
+    }
+
+    /// The empty sound name, which names no sound file and plays nothing.
+    pub const SILENT: &'static str = "";
+
+    /// An explicit silence, serialized as `"sound":""`.
+    ///
+    /// Leaving the sound out omits the key, which is also silent on the
+    /// device, but an explicit silence keeps the key present so that a
+    /// notification service extension, or anything else adding a sound to
+    /// notifications without one, sees that no sound was chosen on purpose.
+    ///
+    /// ```rust
+    /// # use a2::request::payload::APSSound;
+    /// # fn main() {
+    /// assert_eq!("\"\"", serde_json::to_string(&APSSound::silent()).unwrap());
+    /// # }
+    /// ```
+    pub fn silent() -> APSSound<'static> {
+        APSSound::Sound(Cow::Borrowed(Self::SILENT))
+    }
+
+    /// True for the explicit silence.
+    pub fn is_silent(&self) -> bool {
+        matches!(self, APSSound::Sound(name) if name == Self::SILENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::request::notification::{DefaultNotificationBuilder, NotificationBuilder};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_badge_only_payload() {
+        let payload = Payload::badge_only("device-token", 7, Default::default());
+
+        assert_eq!(r#"{"aps":{"badge":7}}"#, payload.to_json_string().unwrap());
+        assert_eq!(Some(PushType::Alert), payload.options.apns_push_type);
+        assert_eq!(Ok(()), Payload::validate_json(&payload.to_json_string().unwrap()));
+
+        let reset = Payload::badge_only("device-token", 0, Default::default());
+
+        assert_eq!(r#"{"aps":{"badge":0}}"#, reset.to_json_string().unwrap());
+    }
+
+    #[derive(Clone)]
+    struct TwoDecimals;
+
+    impl Formatter for TwoDecimals {
+        fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+            write!(writer, "{:.2}", value)
+        }
+    }
+
+    #[test]
+    fn test_data_formatter_only_formats_the_data() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .set_critical_alert(Some(0.5))
+            .build("device-token", Default::default());
+        payload
+            .add_custom_data("price", &json!({"amount": 3.0, "tax": 0.125}))
+            .unwrap();
+        payload.add_custom_data("count", &2).unwrap();
+
+        let json = payload
+            .clone()
+            .with_data_formatter(TwoDecimals)
+            .to_json_string()
+            .unwrap();
+
+        assert!(json.contains(r#""volume":0.5"#), "{}", json);
+        assert!(json.contains(r#""price":{"amount":3.00,"tax":0.12}"#), "{}", json);
+        assert!(json.contains(r#""count":2"#), "{}", json);
+        assert!(json.starts_with(&format!(r#"{{"aps":{},"#, serde_json::to_string(&payload.aps).unwrap())));
+    }
+
+    #[test]
+    fn test_data_formatter_applies_to_serialize() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .build("device-token", Default::default());
+        payload.add_custom_data("price", &json!({"amount": 3.0})).unwrap();
+
+        let formatted = payload.with_data_formatter(TwoDecimals);
+
+        assert_eq!(
+            formatted.to_json_string().unwrap(),
+            serde_json::to_string(&formatted).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_data_formatter_drops_droppable_data() {
+        let mut payload = Payload::badge_only("device-token", 1, Default::default());
+        payload.add_droppable_custom_data("debug", &"x".repeat(5000)).unwrap();
+        payload.add_custom_data("price", &1.5).unwrap();
+
+        let formatted = payload.with_data_formatter(TwoDecimals);
+
+        assert_eq!(
+            r#"{"aps":{"badge":1},"price":1.50}"#,
+            formatted.to_json_string().unwrap()
+        );
+        assert_eq!(32, formatted.size_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_identical_payloads_are_equal() {
+        let build = || {
+            let mut payload = DefaultNotificationBuilder::new()
+                .set_title("a title")
+                .set_body("a body")
+                .set_badge(1)
+                .build("device-token", Default::default());
+            payload.add_custom_data("custom", &json!({"foo": "bar"})).unwrap();
+            payload
+        };
+
+        let (a, b) = (build(), build());
+        assert_eq!(a.to_json_string().unwrap(), b.to_json_string().unwrap());
+        assert_eq!(a, b);
+
+        let set: HashSet<_> = vec![a, b].into_iter().collect();
+        assert_eq!(1, set.len());
+    }
+
+    #[test]
+    fn test_payloads_with_different_content_are_not_equal() {
+        let build = |volume: f64, custom: &str| {
+            let mut payload = DefaultNotificationBuilder::new()
+                .set_body("a body")
+                .set_critical(true, Some(volume))
+                .build("device-token", Default::default());
+            payload.add_custom_data("custom", &custom).unwrap();
+            payload
+        };
+
+        assert_eq!(build(0.5, "foo"), build(0.5, "foo"));
+        assert_ne!(build(0.5, "foo"), build(0.7, "foo"));
+        assert_ne!(build(0.5, "foo"), build(0.5, "bar"));
+
+        let set: HashSet<_> = vec![build(0.5, "foo"), build(0.5, "foo"), build(0.7, "foo")]
+            .into_iter()
+            .collect();
+        assert_eq!(2, set.len());
+    }
+
+    #[test]
+    fn test_droppable_data_is_removed_when_oversized() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .set_title("a title")
+            .build("device-token", Default::default());
+
+        payload.add_custom_data("keep", &"k".repeat(2000)).unwrap();
+        payload.add_droppable_custom_data("first", &"f".repeat(1500)).unwrap();
+        payload.add_droppable_custom_data("second", &"s".repeat(1500)).unwrap();
+
+        assert!(serde_json::to_string(&payload).unwrap().len() > MAX_PAYLOAD_SIZE);
+
+        let json = payload.to_json_string().unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+
+        assert!(json.len() <= MAX_PAYLOAD_SIZE);
+        assert!(value.get("keep").is_some());
+        assert!(value.get("first").is_none());
+        assert!(value.get("second").is_some());
+    }
+
+    #[test]
+    fn test_size_bytes_counts_the_compacted_payload() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .set_title("a title")
+            .build("device-token", Default::default());
+
+        payload.add_custom_data("keep", &"k".repeat(2000)).unwrap();
+        payload.add_droppable_custom_data("first", &"f".repeat(1500)).unwrap();
+        payload.add_droppable_custom_data("second", &"s".repeat(1500)).unwrap();
+
+        let json = payload.to_json_string().unwrap();
+
+        assert!(serde_json::to_string(&payload).unwrap().len() > MAX_PAYLOAD_SIZE);
+        assert_eq!(json.len(), payload.size_bytes().unwrap());
+        assert_eq!(
+            payload.size_bytes().unwrap(),
+            payload.with_data_formatter(CompactFormatter).size_bytes().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_droppable_data_is_kept_when_it_fits() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .set_title("a title")
+            .build("device-token", Default::default());
+
+        payload.add_droppable_custom_data("optional", &"o".repeat(100)).unwrap();
+
+        let value: Value = serde_json::from_str(&payload.to_json_string().unwrap()).unwrap();
+        assert!(value.get("optional").is_some());
+    }
+
+    #[test]
+    fn test_droppable_data_added_again_as_required_is_kept() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .set_title("a title")
+            .build("device-token", Default::default());
+
+        payload.add_droppable_custom_data("context", &"c".repeat(5000)).unwrap();
+        payload.add_custom_data("context", &"c".repeat(10)).unwrap();
+
+        assert!(payload.droppable_data().is_empty());
+
+        payload.add_custom_data("padding", &"p".repeat(5000)).unwrap();
+        let value: Value = serde_json::from_str(&payload.to_json_string().unwrap()).unwrap();
+
+        assert_eq!(Some(&json!("c".repeat(10))), value.get("context"));
+    }
+
+    #[test]
+    fn test_custom_data_keys_in_snake_case() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .set_mutable_content()
+            .set_custom_data_key_case(KeyCase::SnakeCase)
+            .build("device-token", Default::default());
+
+        payload
+            .add_custom_data(
+                "matchId",
+                &json!({ "otherUser": { "displayName": "Ann" }, "photos": [{ "thumbURL": "a" }] }),
+            )
+            .unwrap()
+            .add_droppable_custom_data("debug-info", &1)
+            .unwrap();
+
+        let expected = json!({
+            "aps": {
+                "alert": "a body",
+                "mutable-content": 1,
+            },
+            "match_id": {
+                "other_user": { "display_name": "Ann" },
+                "photos": [{ "thumb_url": "a" }],
+            },
+            "debug_info": 1,
+        });
+
+        assert_eq!(expected, serde_json::to_value(&payload).unwrap());
+        assert_eq!(
+            expected,
+            serde_json::from_str::<Value>(&payload.to_json_string().unwrap()).unwrap()
+        );
+        assert_eq!([Cow::Borrowed("debug-info")], payload.droppable_data());
+    }
+
+    #[test]
+    fn test_key_case_applies_to_keys_added_later() {
+        let mut payload = DefaultNotificationBuilder::new().build("device-token", Default::default());
+
+        payload
+            .add_custom_data("firstKey", &1)
+            .unwrap()
+            .set_custom_data_key_case(Some(KeyCase::KebabCase))
+            .add_custom_data("second_key", &2)
+            .unwrap()
+            .add_custom_data("Third Key", &3)
+            .unwrap();
+
+        assert_eq!(
+            json!({"aps": {"mutable-content": 0}, "first-key": 1, "second-key": 2, "third-key": 3}),
+            serde_json::to_value(&payload).unwrap()
+        );
+
+        payload.set_custom_data_key_case(None);
+
+        assert_eq!(
+            json!({"aps": {"mutable-content": 0}, "firstKey": 1, "second_key": 2, "Third Key": 3}),
+            serde_json::to_value(&payload).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_key_cases() {
+        let table = [
+            ("matchId", "matchId", "match_id", "match-id"),
+            ("match_id", "matchId", "match_id", "match-id"),
+            ("match-id", "matchId", "match_id", "match-id"),
+            ("HTTPServer2Id", "httpServer2Id", "http_server2_id", "http-server2-id"),
+            ("id", "id", "id", "id"),
+            ("__private", "private", "private", "private"),
+        ];
+
+        for (key, camel, snake, kebab) in table {
+            assert_eq!(camel, KeyCase::CamelCase.apply(key), "{}", key);
+            assert_eq!(snake, KeyCase::SnakeCase.apply(key), "{}", key);
+            assert_eq!(kebab, KeyCase::KebabCase.apply(key), "{}", key);
+        }
+    }
+
+    #[test]
+    fn test_can_fit_at_the_size_limit() {
+        let payload = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .build("device-token", Default::default());
+
+        // The payload and `,"extra":""` without the padding.
+        let overhead = payload.size_bytes().unwrap() + 11;
+        let padding = "x".repeat(MAX_PAYLOAD_SIZE - overhead);
+
+        assert!(payload.can_fit(&json!({ "extra": padding })));
+        assert!(!payload.can_fit(&json!({ "extra": format!("{}x", padding) })));
+        assert!(!payload.can_fit(&"not an object"));
+        assert!(payload.data.is_empty());
+    }
+
+    #[test]
+    fn test_voip_payloads_have_a_bigger_limit() {
+        let options = NotificationOptions {
+            apns_push_type: Some(PushType::Voip),
+            ..Default::default()
+        };
+
+        assert_eq!(MAX_VOIP_PAYLOAD_SIZE, max_payload_size(&options));
+        assert_eq!(MAX_PAYLOAD_SIZE, max_payload_size(&Default::default()));
+    }
+
+    #[test]
+    fn test_different_payloads_are_not_equal() {
+        let a = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .build("device-token", Default::default());
+        let b = DefaultNotificationBuilder::new()
+            .set_body("another body")
+            .build("device-token", Default::default());
+        let c = DefaultNotificationBuilder::new()
+            .set_body("a body")
+            .build("other-device-token", Default::default());
+
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+    }
+
+    #[test]
+    fn test_custom_data_is_never_nested_under_a_data_key() {
+        let empty = DefaultNotificationBuilder::new().build("token", Default::default());
+        let empty_json: Value = serde_json::from_str(&empty.to_json_string().unwrap()).unwrap();
+
+        assert_eq!(json!({"aps": {"mutable-content": 0}}), empty_json);
+
+        let mut payload = DefaultNotificationBuilder::new().build("token", Default::default());
+        payload.add_custom_data("foo", &"bar").unwrap();
+
+        // A relay deserializing and serializing the payload again
+        let relayed: Payload = serde_json::from_str(&payload.to_json_string().unwrap()).unwrap();
+        let relayed_json: Value = serde_json::from_str(&relayed.to_json_string().unwrap()).unwrap();
+
+        assert_eq!(json!({"aps": {"mutable-content": 0}, "foo": "bar"}), relayed_json);
+        assert!(relayed_json.get("data").is_none());
+
+        let relayed_empty: Payload = serde_json::from_value(empty_json).unwrap();
+
+        assert!(relayed_empty.aps.extra.is_empty());
+
+        assert!(relayed_empty.data.is_empty());
+        assert_eq!(empty.to_json_string().unwrap(), relayed_empty.to_json_string().unwrap());
+    }
+
+    #[test]
+    fn test_size_bytes_matches_the_serialized_length() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .set_title("a title")
+            .set_body("a body with ünïcödé")
+            .build("token", Default::default());
+        payload.add_custom_data("foo", &json!({"bar": [1, 2, 3]})).unwrap();
+
+        assert_eq!(
+            serde_json::to_vec(&payload).unwrap().len(),
+            payload.size_bytes().unwrap()
+        );
+        assert_eq!(payload.to_json_string().unwrap().len(), payload.size_bytes().unwrap());
+    }
+
+    #[test]
+    fn test_write_json_matches_to_json_string() {
+        let mut payload = DefaultNotificationBuilder::new()
+            .set_title("a title")
+            .set_body("a body with ünïcödé")
+            .build("token", Default::default());
+        payload.add_custom_data("foo", &json!({"bar": [1, 2, 3]})).unwrap();
+
+        let mut written = Vec::new();
+        payload.write_json(&mut written).unwrap();
+        assert_eq!(payload.to_json_string().unwrap().into_bytes(), written);
+
+        payload
+            .add_droppable_custom_data("padding", &"x".repeat(MAX_PAYLOAD_SIZE))
+            .unwrap();
+
+        let mut written = Vec::new();
+        payload.write_json(&mut written).unwrap();
+        assert_eq!(payload.to_json_string().unwrap().into_bytes(), written);
+        assert!(written.len() <= MAX_PAYLOAD_SIZE);
+    }
+
+    #[test]
+    #[cfg(feature = "preserve_order")]
+    fn test_custom_data_keeps_the_insertion_order() {
+        let mut payload = DefaultNotificationBuilder::new().build("token", Default::default());
+        payload.add_custom_data("zulu", &1).unwrap();
+        payload.add_custom_data("alpha", &json!({"y": 1, "x": 2})).unwrap();
+        payload.add_custom_data("mike", &3).unwrap();
+        payload.add_custom_data("zulu", &4).unwrap();
+        payload.data.insert(Cow::Borrowed("bravo"), json!(5));
+
+        assert_eq!(
+            r#"{"aps":{"mutable-content":0},"zulu":4,"alpha":{"x":2,"y":1},"mike":3,"bravo":5}"#,
+            payload.to_json_string().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_default_system_sound() {
+        let sound = APSSound::default_system();
+
+        assert_eq!(json!("default"), serde_json::to_value(&sound).unwrap());
+        assert!(sound.is_default_system());
+
+        let parsed: APSSound = serde_json::from_str(r#""default""#).unwrap();
+
+        assert!(parsed.is_default_system());
+        assert!(!APSSound::Sound("ping.aiff".into()).is_default_system());
+    }
+}
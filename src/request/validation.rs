@@ -0,0 +1,191 @@
+//! Checking payloads against the APNs payload format
+
+use crate::request::notification::InterruptionLevel;
+use crate::request::payload::{APSSound, APS, MAX_PAYLOAD_SIZE};
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+
+/// The documented `aps` keys without a typed field in [`APS`], which are
+/// valid but only settable as extra keys.
+const UNMODELED_APS_KEYS: [&str; 8] = [
+    "thread-id",
+    "target-content-id",
+    "relevance-score",
+    "filter-criteria",
+    "attributes-type",
+    "attributes",
+    "input-push-channel",
+    "input-push-token",
+];
+
+/// A problem found in a payload by
+/// [`Payload::validate_json`](super::payload::Payload::validate_json).
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ValidationIssue {
+    /// The payload is not a JSON object.
+    #[error("The payload is not a JSON object: {0}")]
+    InvalidJson(String),
+
+    /// The payload has no `aps` dictionary and is not an MDM payload.
+    #[error("The payload has no aps dictionary")]
+    MissingAps,
+
+    /// A key in the `aps` dictionary that APNs does not define, most likely
+    /// a typo or custom data that belongs outside of `aps`.
+    #[error("Unknown aps key: {0}")]
+    UnknownApsKey(String),
+
+    /// A value in the `aps` dictionary has the wrong type.
+    #[error("Invalid aps dictionary: {0}")]
+    InvalidAps(String),
+
+    /// Two keys that don't work together.
+    #[error("The keys {first} and {second} conflict, {reason}")]
+    ConflictingKeys {
+        first: &'static str,
+        second: &'static str,
+        reason: &'static str,
+    },
+
+    /// The payload is over the size limit of a regular notification.
+    #[error("The payload is too large: {size} bytes, the maximum is {limit} bytes")]
+    TooLarge { size: usize, limit: usize },
+}
+
+/// The keys of the `aps` dictionary that contradict each other.
+pub(crate) fn conflicts(aps: &APS<'_>) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let critical_sound = matches!(aps.sound, Some(APSSound::Critical(ref sound)) if sound.is_critical());
+
+    if critical_sound && aps.interruption_level != Some(InterruptionLevel::Critical) {
+        issues.push(ValidationIssue::ConflictingKeys {
+            first: "sound",
+            second: "interruption-level",
+            reason: "a critical sound needs the critical interruption level",
+        });
+    }
+
+    issues
+}
+
+pub(crate) fn validate_json(json: &str) -> Result<(), Vec<ValidationIssue>> {
+    let mut issues = Vec::new();
+
+    if json.len() > MAX_PAYLOAD_SIZE {
+        issues.push(ValidationIssue::TooLarge {
+            size: json.len(),
+            limit: MAX_PAYLOAD_SIZE,
+        });
+    }
+
+    let root = match serde_json::from_str::<Value>(json) {
+        Ok(Value::Object(root)) => root,
+        Ok(_) => {
+            issues.push(ValidationIssue::InvalidJson(String::from("expected an object")));
+            return Err(issues);
+        }
+        Err(e) => {
+            issues.push(ValidationIssue::InvalidJson(e.to_string()));
+            return Err(issues);
+        }
+    };
+
+    match root.get("aps") {
+        Some(Value::Object(aps)) => {
+            issues.extend(
+                aps.keys()
+                    .filter(|key| !APS::KEYS.contains(&key.as_str()) && !UNMODELED_APS_KEYS.contains(&key.as_str()))
+                    .map(|key| ValidationIssue::UnknownApsKey(key.clone())),
+            );
+
+            match APS::deserialize(&root["aps"]) {
+                Ok(aps) => issues.extend(conflicts(&aps)),
+                Err(e) => issues.push(ValidationIssue::InvalidAps(e.to_string())),
+            }
+        }
+        Some(_) => issues.push(ValidationIssue::InvalidAps(String::from("expected a dictionary"))),
+        None if root.contains_key("mdm") => (),
+        None => issues.push(ValidationIssue::MissingAps),
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(issues)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_payloads() {
+        assert_eq!(
+            Ok(()),
+            validate_json(r#"{"aps":{"alert":"Hi","thread-id":"chat"},"foo":1}"#)
+        );
+        assert_eq!(Ok(()), validate_json(r#"{"mdm":"magic"}"#));
+    }
+
+    #[test]
+    fn test_malformed_payload_issues() {
+        let json = json!({
+            "aps": {
+                "alert": "Hi",
+                "badge": "one",
+                "sond": "ping.aiff",
+            },
+            "padding": "x".repeat(MAX_PAYLOAD_SIZE),
+        })
+        .to_string();
+
+        let issues = validate_json(&json).unwrap_err();
+
+        assert_eq!(3, issues.len());
+        assert_eq!(
+            ValidationIssue::TooLarge {
+                size: json.len(),
+                limit: MAX_PAYLOAD_SIZE
+            },
+            issues[0]
+        );
+        assert_eq!(ValidationIssue::UnknownApsKey(String::from("sond")), issues[1]);
+        assert!(matches!(issues[2], ValidationIssue::InvalidAps(_)));
+    }
+
+    #[test]
+    fn test_conflicting_keys() {
+        let issues = validate_json(r#"{"aps":{"sound":{"critical":1,"name":"siren.aiff"}}}"#).unwrap_err();
+
+        assert!(matches!(
+            issues[..],
+            [ValidationIssue::ConflictingKeys {
+                first: "sound",
+                second: "interruption-level",
+                ..
+            }]
+        ));
+        assert_eq!(
+            Ok(()),
+            validate_json(r#"{"aps":{"sound":{"critical":1},"interruption-level":"critical"}}"#)
+        );
+    }
+
+    #[test]
+    fn test_not_an_object() {
+        assert!(matches!(
+            validate_json("[1, 2]").unwrap_err()[..],
+            [ValidationIssue::InvalidJson(_)]
+        ));
+        assert!(matches!(
+            validate_json("{").unwrap_err()[..],
+            [ValidationIssue::InvalidJson(_)]
+        ));
+        assert_eq!(
+            Err(vec![ValidationIssue::MissingAps]),
+            validate_json(r#"{"foo":"bar"}"#)
+        );
+    }
+}
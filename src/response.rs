@@ -1,17 +1,34 @@
 //! The APNs response types
 
+#[cfg(feature = "client")]
+use http::{HeaderMap, StatusCode};
+#[cfg(feature = "client")]
+use hyper::body::Bytes;
 use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// The response data from APNs.
+///
+/// More fields may be added in minor releases, so it can't be built with a
+/// struct literal outside of this crate. [`Response::new`] builds one, e.g.
+/// for testing code that handles responses.
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct Response {
     /// If the notification was not successful, has the body content from APNs.
     pub error: Option<ErrorBody>,
 
     /// Is the value defined in the `NotificationOptions` or a new Uuid
-    /// generated by APNs.
+    /// generated by APNs. Taken from the `apns-id` response header, falling
+    /// back to the id of the `NotificationOptions` if the header is missing.
     pub apns_id: Option<String>,
 
+    /// The id APNs gives a notification sent to the sandbox environment,
+    /// also for successful sends, for looking up its delivery log in the
+    /// Push Notifications Console. Taken from the `apns-unique-id` response
+    /// header, which APNs doesn't send in production.
+    pub apns_unique_id: Option<String>,
+
     /// The HTTP response code.
     ///
     /// * 200 Success
@@ -24,6 +41,110 @@ pub struct Response {
     /// * 500 Internal server error.
     /// * 503 The server is shutting down and unavailable.
     pub code: u16,
+
+    /// The size in bytes of the payload that was sent, to tell how far over
+    /// the limit a payload rejected with `PayloadTooLarge` was.
+    pub payload_size: usize,
+
+    /// A likely cause for the error, e.g. a `BadDeviceToken` from a token
+    /// issued for the other APNs environment.
+    pub hint: Option<String>,
+
+    /// The number of attempts it took to send the notification, more than
+    /// one if [`Client::send_with_retry`](../client/struct.Client.html#method.send_with_retry)
+    /// retried it.
+    pub attempts: u32,
+
+    /// The body of an error response that is not the JSON APNs sends, e.g.
+    /// an HTML page from a proxy.
+    pub unparsed_body: Option<String>,
+
+    /// The seconds to wait before sending again, from a `Retry-After`
+    /// header in either delta-seconds or HTTP-date form. See
+    /// [`retry_after`](Self::retry_after) for it as a `Duration`.
+    pub retry_after_secs: Option<u32>,
+
+    /// The time of the APNs server in seconds since the Unix epoch, from the
+    /// `Date` header. See [`server_time`](Self::server_time) for it as a
+    /// `SystemTime`.
+    pub server_time_secs: Option<u64>,
+
+    /// An id of the send, the same for all of its attempts when it was
+    /// retried, for correlating the logs of the attempts. Unlike the
+    /// `apns_id` it is never sent to APNs. Taken from
+    /// [`SendOptions::correlation_id`](../client/struct.SendOptions.html#structfield.correlation_id)
+    /// or generated for every send, unique within the process.
+    pub correlation_id: u64,
+}
+
+impl Response {
+    /// A response with the given status code and error reason, taking a
+    /// single attempt and without any of the optional response headers.
+    ///
+    /// ```rust
+    /// # use a2::{ErrorReason, Response};
+    /// # fn main() {
+    /// let response = Response::new(410, Some(ErrorReason::Unregistered));
+    ///
+    /// assert!(response.should_remove_token());
+    /// # }
+    /// ```
+    pub fn new(code: u16, reason: Option<ErrorReason>) -> Self {
+        Self {
+            error: reason.map(|reason| ErrorBody {
+                reason,
+                timestamp: None,
+            }),
+            apns_id: None,
+            apns_unique_id: None,
+            code,
+            payload_size: 0,
+            hint: None,
+            attempts: 1,
+            unparsed_body: None,
+            retry_after_secs: None,
+            server_time_secs: None,
+            correlation_id: 0,
+        }
+    }
+
+    /// How long to wait before sending again, if APNs or a proxy sent a
+    /// `Retry-After` header.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after_secs.map(|secs| Duration::from_secs(secs.into()))
+    }
+
+    /// The time of the APNs server when it responded, if the response had a
+    /// `Date` header. Comparing it with the local clock tells how far off the
+    /// local clock is, e.g. to pick a
+    /// [`ClientConfig::with_clock_skew`](../client/struct.ClientConfig.html#method.with_clock_skew)
+    /// for a host whose clock runs ahead. The header has a resolution of one
+    /// second.
+    pub fn server_time(&self) -> Option<SystemTime> {
+        self.server_time_secs.map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Whether APNs reported the device token as no longer active for the
+    /// topic, with the status 410 or the reason `Unregistered`, so it should
+    /// be removed from the stored tokens.
+    pub fn should_remove_token(&self) -> bool {
+        self.code == 410 || self.error.map(|error| error.reason) == Some(ErrorReason::Unregistered)
+    }
+}
+
+/// The HTTP response from APNs as it was received, returned from
+/// [`Client::send_raw_response`](../client/struct.Client.html#method.send_raw_response).
+#[cfg(feature = "client")]
+#[derive(Clone, Debug)]
+pub struct RawResponse {
+    /// The HTTP status of the response.
+    pub status: StatusCode,
+
+    /// All headers of the response.
+    pub headers: HeaderMap,
+
+    /// The response body.
+    pub body: Bytes,
 }
 
 /// The response body from APNs. Only available for errors.
@@ -42,7 +163,7 @@ pub struct ErrorBody {
 }
 
 /// A description what went wrong with the push notification.
-#[derive(Copy, Clone, Deserialize, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Deserialize, Debug, PartialEq, Eq, Hash)]
 pub enum ErrorReason {
     /// The collapse identifier exceeds the maximum allowed size.
     BadCollapseId,
@@ -135,6 +256,41 @@ pub enum ErrorReason {
     Shutdown,
 }
 
+impl ErrorReason {
+    /// Whether sending the same notification again later can succeed. True
+    /// for throttling, for server errors and for idle connections closed by
+    /// APNs.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ErrorReason::TooManyRequests
+                | ErrorReason::InternalServerError
+                | ErrorReason::ServiceUnavailable
+                | ErrorReason::Shutdown
+                | ErrorReason::IdleTimeout
+        )
+    }
+
+    /// The status of [`Error::suggested_http_status`](crate::Error::suggested_http_status)
+    /// for an error response from APNs with this reason.
+    pub(crate) fn suggested_http_status(&self) -> u16 {
+        match self {
+            ErrorReason::BadCollapseId
+            | ErrorReason::BadDeviceToken
+            | ErrorReason::BadExpirationDate
+            | ErrorReason::BadMessageId
+            | ErrorReason::BadPriority
+            | ErrorReason::DeviceTokenNotForTopic
+            | ErrorReason::MissingDeviceToken
+            | ErrorReason::PayloadEmpty => 400,
+            ErrorReason::Unregistered => 410,
+            ErrorReason::PayloadTooLarge => 413,
+            ErrorReason::TooManyRequests => 429,
+            _ => 502,
+        }
+    }
+}
+
 impl fmt::Display for ErrorReason {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match *self {